@@ -1,32 +1,52 @@
 use bevy::{
     app::{App, Startup, Update},
+    core::FrameCountPlugin,
+    diagnostic::{DiagnosticsStore, FrameTimeDiagnosticsPlugin, LogDiagnosticsPlugin},
     ecs::{
         component::Component,
         query::With,
-        system::{Commands, Query, Res},
+        system::{Commands, Query, Res, ResMut},
     },
+    hierarchy::BuildChildren,
+    log::LogPlugin,
     time::{Time, TimePlugin},
 };
 use game::{
-    math::{Motor, Vector3},
-    render::{Camera, MainCamera, Material, Sphere},
-    transform::Transform,
+    math::{Motor, Vector2, Vector3},
+    render::{
+        Bloom, Camera, Fog, Gizmos, MainCamera, Material, Materials, Medium, OverlayRect,
+        OverlayText, PostProcess, RenderDiagnostics, Sdf, SdfOperation, SdfPrimitive, SdfShape,
+        Sphere, Vignette,
+    },
+    transform::{GlobalTransform, Transform},
     GamePlugins,
 };
 
 fn main() {
     App::new()
-        .add_plugins(GamePlugins)
-        .add_plugins(TimePlugin)
+        .add_plugins((LogPlugin::default(), GamePlugins))
+        .add_plugins((
+            TimePlugin,
+            FrameCountPlugin,
+            LogDiagnosticsPlugin::default(),
+        ))
+        .insert_resource(Fog {
+            density: 0.02,
+            height: -2.0,
+            ..Default::default()
+        })
         .add_systems(Startup, startup)
-        .add_systems(Update, spiral_spheres)
+        .add_systems(Update, (spiral_spheres, draw_gizmos, update_fps_text))
         .run()
 }
 
 #[derive(Component)]
 struct SpiralMove;
 
-fn startup(mut commands: Commands) {
+#[derive(Component)]
+struct FpsText;
+
+fn startup(mut commands: Commands, mut materials: ResMut<Materials>) {
     commands.spawn((
         Transform {
             motor: Motor::translation(Vector3 {
@@ -45,6 +65,13 @@ fn startup(mut commands: Commands) {
                 z: 0.2,
             }
             .normalized(),
+            shutter_open: 0.5,
+            shutter_close: 1.0,
+        },
+        PostProcess {
+            bloom: Some(Bloom::default()),
+            vignette: Some(Vignette::default()),
+            ..Default::default()
         },
         MainCamera,
     ));
@@ -57,40 +84,166 @@ fn startup(mut commands: Commands) {
                 z: 0.0,
             }),
         },
-        Sphere { radius: 100.0 },
-        Material {
+        Sphere {
+            radius: 100.0,
+            medium: None,
+        },
+        materials.add(Material {
             color: Vector3 {
                 x: 0.8,
                 y: 0.8,
                 z: 0.8,
             },
-        },
+            emission: Vector3 {
+                x: 0.0,
+                y: 0.0,
+                z: 0.0,
+            },
+        }),
     ));
     commands.spawn((
         Transform {
             motor: Motor::IDENTITY,
         },
-        Sphere { radius: 1.0 },
-        Material {
+        Sphere {
+            radius: 1.0,
+            medium: None,
+        },
+        materials.add(Material {
             color: Vector3 {
                 x: 0.1,
                 y: 0.8,
                 z: 0.2,
             },
-        },
+            emission: Vector3 {
+                x: 0.5,
+                y: 2.0,
+                z: 0.8,
+            },
+        }),
         SpiralMove,
     ));
+    commands.spawn((
+        Transform {
+            motor: Motor::translation(Vector3 {
+                x: 3.0,
+                y: 0.0,
+                z: -2.0,
+            }),
+        },
+        Sphere {
+            radius: 1.5,
+            medium: Some(Medium {
+                density: 1.0,
+                anisotropy: 0.3,
+            }),
+        },
+        materials.add(Material {
+            color: Vector3 {
+                x: 0.9,
+                y: 0.9,
+                z: 0.9,
+            },
+            emission: Vector3 {
+                x: 0.0,
+                y: 0.0,
+                z: 0.0,
+            },
+        }),
+    ));
+    commands
+        .spawn((
+            Transform {
+                motor: Motor::translation(Vector3 {
+                    x: 2.0,
+                    y: -1.0,
+                    z: 2.5,
+                }),
+            },
+            Sdf::default(),
+            SdfShape {
+                primitive: SdfPrimitive::RoundedBox {
+                    half_size: Vector3 {
+                        x: 0.6,
+                        y: 0.6,
+                        z: 0.6,
+                    },
+                    radius: 0.1,
+                },
+                operation: SdfOperation::Union,
+                smoothness: 0.0,
+            },
+            materials.add(Material {
+                color: Vector3 {
+                    x: 0.8,
+                    y: 0.3,
+                    z: 0.2,
+                },
+                emission: Vector3 {
+                    x: 0.0,
+                    y: 0.0,
+                    z: 0.0,
+                },
+            }),
+        ))
+        .with_children(|parent| {
+            // the shapes of the children blend into the box of their parent
+            parent.spawn((
+                Transform {
+                    motor: Motor::IDENTITY,
+                },
+                SdfShape {
+                    primitive: SdfPrimitive::Torus {
+                        major_radius: 0.9,
+                        minor_radius: 0.15,
+                    },
+                    operation: SdfOperation::Union,
+                    smoothness: 0.3,
+                },
+            ));
+            parent.spawn((
+                Transform {
+                    motor: Motor::IDENTITY,
+                },
+                SdfShape {
+                    primitive: SdfPrimitive::Capsule {
+                        half_length: 1.0,
+                        radius: 0.35,
+                    },
+                    operation: SdfOperation::Subtract,
+                    smoothness: 0.1,
+                },
+            ));
+        });
+
+    commands.spawn(OverlayRect {
+        position: Vector2 { x: 0.0, y: 0.0 },
+        size: Vector2 { x: 360.0, y: 24.0 },
+        color: Vector3 {
+            x: 0.0,
+            y: 0.0,
+            z: 0.0,
+        },
+    });
+    commands.spawn((
+        OverlayText {
+            text: String::new(),
+            position: Vector2 { x: 4.0, y: 4.0 },
+            scale: 2.0,
+            color: Vector3 {
+                x: 1.0,
+                y: 1.0,
+                z: 1.0,
+            },
+        },
+        FpsText,
+    ));
 }
 
 fn spiral_spheres(
     mut spheres: Query<&mut Transform, (With<Sphere>, With<SpiralMove>)>,
     time: Res<Time>,
 ) {
-    print!(
-        "\r{:.3}ms or {:.3} FPS        ",
-        time.delta_seconds_f64() * 1000.0,
-        1.0 / time.delta_seconds_f64()
-    );
     spheres.for_each_mut(|mut sphere| {
         let time = time.elapsed_seconds() * 2.0;
         sphere.motor = Motor::translation(Vector3 {
@@ -100,3 +253,53 @@ fn spiral_spheres(
         });
     });
 }
+
+fn draw_gizmos(spheres: Query<(&GlobalTransform, &Sphere), With<SpiralMove>>, mut gizmos: Gizmos) {
+    spheres.for_each(|(global_transform, sphere)| {
+        let motor = global_transform.transform().motor;
+        gizmos.axes(motor, sphere.radius * 1.5);
+        gizmos.wire_sphere(
+            motor,
+            sphere.radius,
+            Vector3 {
+                x: 1.0,
+                y: 1.0,
+                z: 0.0,
+            },
+        );
+    });
+}
+
+fn update_fps_text(
+    mut texts: Query<&mut OverlayText, With<FpsText>>,
+    diagnostics_store: Res<DiagnosticsStore>,
+    diagnostics: Res<RenderDiagnostics>,
+) {
+    let smoothed = |id| {
+        diagnostics_store
+            .get(id)
+            .and_then(|diagnostic| diagnostic.smoothed())
+            .unwrap_or_default()
+    };
+    texts.for_each_mut(|mut text| {
+        text.text = format!(
+            "{:.3}ms or {:.3} FPS at {:.0}% resolution",
+            smoothed(FrameTimeDiagnosticsPlugin::FRAME_TIME),
+            smoothed(FrameTimeDiagnosticsPlugin::FPS),
+            smoothed(RenderDiagnostics::RENDER_SCALE) * 100.0
+        );
+        for (pass, milliseconds) in &diagnostics.gpu_pass_times {
+            text.text += &format!("\n{pass}: {milliseconds:.3}ms");
+        }
+        if let Some(ray_statistics) = diagnostics.ray_statistics {
+            text.text += &format!(
+                "\n{} rays, {:.1} tests per ray",
+                ray_statistics.rays(),
+                ray_statistics.tests_per_ray
+            );
+            if let Some(rays_per_second) = ray_statistics.rays_per_second {
+                text.text += &format!(", {:.1} million rays per second", rays_per_second / 1.0e6);
+            }
+        }
+    });
+}
@@ -1,7 +1,7 @@
 use crate::math::Vector3;
 use encase::ShaderType;
 
-#[derive(Debug, Clone, Copy, ShaderType)]
+#[derive(Debug, Clone, Copy, PartialEq, ShaderType)]
 pub struct Motor {
     pub s: f32,
     pub e12: f32,
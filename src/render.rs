@@ -1,39 +1,176 @@
+mod color_grading;
+mod diagnostics;
+mod gizmos;
+mod gpu_array_buffer;
+mod gpu_bytes_buffer;
+mod gpu_slot_buffer;
+mod material;
+mod offline_render;
+mod post_process;
+mod primitive;
+mod recorder;
+mod render_error;
+mod render_graph;
+mod render_scale;
+mod render_settings;
 mod render_state;
+mod sdf;
+mod shader;
+mod shader_layout;
+mod tiled_render;
+
+pub use color_grading::{ColorGrading, CubeLutError, Lut3d, Tonemapping};
+pub use diagnostics::{CountRays, RayStatistics, RenderDiagnostics};
+pub use gizmos::Gizmos;
+pub use gpu_array_buffer::GpuArrayBuffer;
+pub use gpu_slot_buffer::GpuSlotBuffer;
+pub use material::{Material, MaterialHandle, Materials};
+pub use offline_render::{RenderStill, RenderStillError};
+pub use post_process::{Bloom, ChromaticAberration, FilmGrain, PostProcess, Vignette};
+pub use primitive::{Primitive, PrimitiveAppExt};
+pub use recorder::Recorder;
+pub use render_error::{RenderError, RenderStatus};
+pub use render_graph::{
+    GraphBuffer, GraphTexture, RenderContext, RenderGraph, RenderGraphError, RenderGraphResources,
+    RenderNode, TextureSize, DEPTH_TEXTURE, GIZMO_NODE, HDR_TEXTURE, MAIN_TEXTURE, OVERLAY_NODE,
+    POST_PROCESS_NODE, PRESENT_NODE, RAY_TRACING_NODE,
+};
+pub use render_scale::{RenderScale, UpscaleFilter};
+pub use render_settings::RenderSettings;
+pub use sdf::{Sdf, SdfOperation, SdfPrimitive, SdfShape, MAX_SDF_SHAPES};
+pub use shader::{compose_shader, ShaderComposeError, ShaderHotReload, ShaderLocation};
+pub use shader_layout::{
+    validate_struct_layouts, FieldMetadata, ShaderLayoutError, ShaderStruct, StructLayout,
+};
+pub use tiled_render::{RenderedImage, TileProgress, TiledRender, TiledRenderError};
 
 use crate::{
-    math::Vector3,
-    render::render_state::{RenderState, SphereState},
+    math::{Vector2, Vector3},
+    render::{
+        gizmos::GizmoBuffer,
+        render_state::{RenderRecovery, RenderState},
+    },
+    transform,
 };
 use bevy::{
-    app::{App, Plugin},
+    app::{App, First, Plugin, PostUpdate},
+    diagnostic::{Diagnostic, FrameTimeDiagnosticsPlugin, RegisterDiagnostic},
     ecs::{
         component::Component,
-        schedule::{IntoSystemConfigs, Schedule, ScheduleLabel},
+        schedule::{
+            common_conditions::resource_exists, IntoSystemConfigs, IntoSystemSetConfigs, Schedule,
+            ScheduleLabel, SystemSet,
+        },
+        system::Resource,
     },
+    time::TimeSystem,
 };
 
+/// needs bevy's `TimePlugin` and `FrameCountPlugin` for the [`FrameTimeDiagnosticsPlugin`] it adds
 pub struct RenderPlugin;
 
 impl Plugin for RenderPlugin {
     fn build(&self, app: &mut App) {
-        app.init_resource::<RenderState>()
-            .init_resource::<SphereState>();
+        app.add_event::<RenderError>()
+            .init_resource::<RenderStatus>()
+            .init_resource::<RenderSettings>()
+            .init_resource::<RenderScale>()
+            .init_resource::<UpscaleFilter>()
+            .init_resource::<DebugView>()
+            .init_resource::<Fog>()
+            .init_resource::<MediaRendering>()
+            .init_resource::<RenderDiagnostics>()
+            .init_resource::<Materials>()
+            .init_resource::<GizmoBuffer>()
+            .init_resource::<RenderRecovery>()
+            .init_resource::<RenderGraph>();
+        // when this fails the app keeps running and the renderer is created again later
+        render_state::create_renderer(&mut app.world);
+
+        // the adaptive render scale follows the frame time
+        if !app.is_plugin_added::<FrameTimeDiagnosticsPlugin>() {
+            app.add_plugins(FrameTimeDiagnosticsPlugin);
+        }
+        app.register_diagnostic(Diagnostic::new(
+            RenderDiagnostics::PRIMITIVE_COUNT,
+            "primitive_count",
+            1,
+        ))
+        .register_diagnostic(
+            Diagnostic::new(RenderDiagnostics::UPLOADED_BYTES, "uploaded_bytes", 20)
+                .with_suffix("B"),
+        )
+        .register_diagnostic(Diagnostic::new(
+            RenderDiagnostics::SURFACE_CONFIGURATIONS,
+            "surface_configurations",
+            1,
+        ))
+        .register_diagnostic(Diagnostic::new(
+            RenderDiagnostics::RENDER_SCALE,
+            "render_scale",
+            1,
+        ));
+
+        app.add_systems(First, gizmos::clear_gizmos)
+            .add_systems(First, recorder::update_recording.before(TimeSystem))
+            // the shapes are posed by their global transforms of this update
+            .add_systems(
+                PostUpdate,
+                sdf::collect_sdf_shapes.after(transform::update_global_transforms),
+            );
 
         let mut render_schedule = Schedule::new(RenderSchedule);
-        render_schedule.add_systems(
+        render_schedule.configure_sets(
             (
-                (render_state::update_camera, render_state::update_spheres),
-                render_state::render,
+                RenderSet::Recreate,
+                RenderSet::Update,
+                RenderSet::Prepare,
+                RenderSet::Render,
             )
                 .chain(),
         );
+        for set in [RenderSet::Update, RenderSet::Prepare, RenderSet::Render] {
+            render_schedule.configure_sets(set.run_if(resource_exists::<RenderState>()));
+        }
+        render_schedule.add_systems((
+            render_state::recreate_renderer.in_set(RenderSet::Recreate),
+            (
+                render_state::update_present_mode,
+                render_state::update_render_scale,
+                render_state::update_camera,
+                render_state::update_gizmos,
+                render_state::update_overlay,
+                render_state::update_post_process,
+                render_state::update_materials,
+                render_state::hot_reload_shaders,
+            )
+                .in_set(RenderSet::Update),
+            render_state::prepare_primitives.in_set(RenderSet::Prepare),
+            (render_state::render, render_state::measure_diagnostics)
+                .chain()
+                .in_set(RenderSet::Render),
+        ));
         app.world.add_schedule(render_schedule);
+
+        app.register_primitive::<Sphere>()
+            .register_primitive::<Sdf>();
     }
 }
 
 #[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash, ScheduleLabel)]
 pub struct RenderSchedule;
 
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, SystemSet)]
+enum RenderSet {
+    /// creates the renderer again after it failed, the other sets only run while there is a renderer
+    Recreate,
+    /// uploads the components to the gpu
+    Update,
+    /// recreates the bind groups and pipelines that depend on what was uploaded
+    Prepare,
+    Render,
+}
+
 #[derive(Component)]
 pub struct MainCamera;
 
@@ -43,14 +180,105 @@ pub struct Camera {
     pub min_distance: f32,
     pub max_distance: f32,
     pub sun_direction: Vector3,
+    /// when the shutter opens and closes for motion blur, from 0 at the previous update to 1 at the current one,
+    /// every ray is sent at a random time in between, they are equal for no motion blur
+    pub shutter_open: f32,
+    pub shutter_close: f32,
+}
+
+#[derive(Component)]
+pub struct Sphere {
+    pub radius: f32,
+    /// makes the sphere a volume of particles that scatter and absorb light instead of a surface,
+    /// the color of its material is the albedo of the particles and the emission is the light they emit
+    pub medium: Option<Medium>,
+}
+
+/// a volume with the same density everywhere, like smoke or a cloud
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Medium {
+    /// how much light is scattered or absorbed per unit of distance
+    pub density: f32,
+    /// from -1 to 1, how much more light is scattered backward or forward, 0 scatters it the same in every direction
+    pub anisotropy: f32,
+}
+
+/// fog that fills the whole scene, its density falls off exponentially with the height
+///
+/// the fog scatters sunlight toward the camera, so the shadows of objects in it show up as god rays
+#[derive(Resource, Debug, Clone, Copy)]
+pub struct Fog {
+    /// the density at `height`, 0 disables the fog
+    pub density: f32,
+    pub height: f32,
+    /// how quickly the density falls off above `height` and grows below it, 0 is the same density everywhere
+    pub height_falloff: f32,
+    /// how much of the light the fog scatters instead of absorbing it
+    pub albedo: Vector3,
+    /// from -1 to 1, like [`Medium::anisotropy`]
+    pub anisotropy: f32,
+}
+
+impl Default for Fog {
+    fn default() -> Self {
+        Self {
+            density: 0.0,
+            height: 0.0,
+            height_falloff: 0.5,
+            albedo: Vector3 {
+                x: 0.9,
+                y: 0.9,
+                z: 0.9,
+            },
+            anisotropy: 0.6,
+        }
+    }
 }
 
+/// how the [`Fog`] and [`Medium`]s are rendered
+#[derive(Resource, Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub enum MediaRendering {
+    /// the transmittance is calculated in closed form and light is scattered once toward the camera,
+    /// which is cheap enough for interactive use
+    #[default]
+    Analytic,
+    /// where light scatters is sampled with delta tracking and the transmittance toward the sun with ratio tracking,
+    /// the light can bounce `max_bounces` times, 0 only scatters sunlight once toward the camera,
+    /// this is noisy and meant for many samples per pixel like in a [`TiledRender`]
+    PathTraced { max_bounces: u32 },
+}
+
+/// a rectangle drawn on top of everything else, `position` and `size` are in pixels from the top left of the window
 #[derive(Component)]
-pub struct Material {
+pub struct OverlayRect {
+    pub position: Vector2,
+    pub size: Vector2,
     pub color: Vector3,
 }
 
+/// text drawn on top of everything else using a builtin 8x8 pixel font, `scale` is how many window pixels each font pixel covers
+///
+/// text is always drawn above every [`OverlayRect`]
 #[derive(Component)]
-pub struct Sphere {
-    pub radius: f32,
+pub struct OverlayText {
+    pub text: String,
+    pub position: Vector2,
+    pub scale: f32,
+    pub color: Vector3,
+}
+
+#[derive(Resource, Debug, Default, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum DebugView {
+    #[default]
+    Shaded,
+    Normals,
+    Depth,
+    Albedo,
+    /// heatmap of the distance from the camera to the closest hit
+    HitDistance,
+    /// white where the sun is visible from the hit point, black where the shadow ray was blocked,
+    /// gray where fog and media absorb some of the sunlight
+    ShadowMask,
+    /// heatmap of how many primitive intersection tests were done for the pixel, including shadow rays
+    IntersectionTests,
 }
@@ -0,0 +1,21 @@
+#import math
+
+// has to match `GpuCamera`
+struct Camera {
+    transform: Motor,
+    v_fov: f32,
+    min_distance: f32,
+    max_distance: f32,
+    sun_direction: vec3<f32>,
+    debug_view: u32,
+    shutter_open: f32,
+    shutter_close: f32,
+    fog_albedo: vec3<f32>,
+    fog_density: f32,
+    fog_height: f32,
+    fog_height_falloff: f32,
+    fog_anisotropy: f32,
+    // has to match `MediaRendering`
+    media_rendering: u32,
+    max_bounces: u32,
+}
@@ -0,0 +1,370 @@
+use crate::math::Vector3;
+use bevy::ecs::component::Component;
+use std::{fmt, io, path::Path, sync::Arc};
+
+/// color adjustments applied to the image of the camera it is attached to, after the hdr effects of [`PostProcess`](super::PostProcess)
+///
+/// the adjustments are applied in the order of the fields, so the lut sees the tonemapped image
+#[derive(Component, Clone)]
+pub struct ColorGrading {
+    /// in stops, every +1 doubles the brightness
+    pub exposure: f32,
+    /// shifts the white balance towards blue for negative values and towards yellow for positive values, in the range -100 to 100
+    pub temperature: f32,
+    /// shifts the white balance towards green for negative values and towards magenta for positive values, in the range -100 to 100
+    pub tint: f32,
+    pub contrast: f32,
+    pub saturation: f32,
+    pub lift: Vector3,
+    pub gamma: Vector3,
+    pub gain: Vector3,
+    pub tonemapping: Tonemapping,
+    pub lut: Option<Arc<Lut3d>>,
+}
+
+impl Default for ColorGrading {
+    fn default() -> Self {
+        Self {
+            exposure: 0.0,
+            temperature: 0.0,
+            tint: 0.0,
+            contrast: 1.0,
+            saturation: 1.0,
+            lift: Vector3 {
+                x: 0.0,
+                y: 0.0,
+                z: 0.0,
+            },
+            gamma: Vector3 {
+                x: 1.0,
+                y: 1.0,
+                z: 1.0,
+            },
+            gain: Vector3 {
+                x: 1.0,
+                y: 1.0,
+                z: 1.0,
+            },
+            tonemapping: Tonemapping::None,
+            lut: None,
+        }
+    }
+}
+
+impl ColorGrading {
+    /// the per channel scale in lms space that applies `temperature` and `tint`
+    pub(super) fn white_balance(&self) -> Vector3 {
+        let temperature = self.temperature / 65.0;
+        let tint = self.tint / 65.0;
+
+        // the chromaticity of the new white point, moved along the daylight locus
+        let x = 0.31271 - temperature * if temperature < 0.0 { 0.1 } else { 0.05 };
+        let y = 2.87 * x - 3.0 * x * x - 0.275_095_07 + tint * 0.05;
+
+        let (big_x, big_y, big_z) = (x / y, 1.0, (1.0 - x - y) / y);
+        let l = 0.7328 * big_x + 0.4296 * big_y - 0.1624 * big_z;
+        let m = -0.7036 * big_x + 1.6975 * big_y + 0.0061 * big_z;
+        let s = 0.0030 * big_x + 0.0136 * big_y + 0.9834 * big_z;
+
+        // the d65 white point in lms space
+        Vector3 {
+            x: 0.949237 / l,
+            y: 1.03542 / m,
+            z: 1.08728 / s,
+        }
+    }
+}
+
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Hash)]
+pub enum Tonemapping {
+    /// colors are clamped to 0-1 when they are written to the screen
+    #[default]
+    None,
+    Reinhard,
+    /// the curve fit of the aces filmic tonemapper by Krzysztof Narkowicz
+    Aces,
+}
+
+/// a 3d color lookup table, usually loaded from a `.cube` file exported by a color grading tool
+#[derive(Clone, Debug)]
+pub struct Lut3d {
+    size: u32,
+    domain_min: Vector3,
+    domain_max: Vector3,
+    // red changes fastest, then green, then blue
+    data: Vec<[f32; 3]>,
+}
+
+impl Lut3d {
+    pub fn identity(size: u32) -> Self {
+        let size = size.max(2);
+        let scale = 1.0 / (size - 1) as f32;
+        let data = (0..size)
+            .flat_map(|b| (0..size).flat_map(move |g| (0..size).map(move |r| (r, g, b))))
+            .map(|(r, g, b)| [r as f32 * scale, g as f32 * scale, b as f32 * scale])
+            .collect();
+        Self {
+            size,
+            domain_min: Vector3 {
+                x: 0.0,
+                y: 0.0,
+                z: 0.0,
+            },
+            domain_max: Vector3 {
+                x: 1.0,
+                y: 1.0,
+                z: 1.0,
+            },
+            data,
+        }
+    }
+
+    pub fn load_cube(path: impl AsRef<Path>) -> Result<Self, CubeLutError> {
+        Self::parse_cube(&std::fs::read_to_string(path).map_err(CubeLutError::Io)?)
+    }
+
+    /// parses the `TITLE`, `LUT_3D_SIZE`, `DOMAIN_MIN`, `DOMAIN_MAX` and `LUT_3D_INPUT_RANGE` keywords and the entries,
+    /// any other keyword is an error, so a lut that needs it is not applied wrong
+    pub fn parse_cube(source: &str) -> Result<Self, CubeLutError> {
+        let mut size = None;
+        let mut domain_min = Vector3 {
+            x: 0.0,
+            y: 0.0,
+            z: 0.0,
+        };
+        let mut domain_max = Vector3 {
+            x: 1.0,
+            y: 1.0,
+            z: 1.0,
+        };
+        let mut data = vec![];
+
+        for (index, line) in source.lines().enumerate() {
+            let line_number = index + 1;
+            let error = |message: &str| CubeLutError::Parse {
+                line: line_number,
+                message: message.to_string(),
+            };
+            let parse_floats = |values: &[&str]| {
+                let mut floats = [0.0; 3];
+                if values.len() != floats.len() {
+                    return Err(error("expected 3 numbers"));
+                }
+                for (float, value) in floats.iter_mut().zip(values) {
+                    *float = value.parse().map_err(|_| error("invalid number"))?;
+                }
+                Ok(floats)
+            };
+
+            let line = line.trim();
+            if line.is_empty() || line.starts_with('#') {
+                continue;
+            }
+
+            let words = line.split_whitespace().collect::<Vec<_>>();
+            match words[0] {
+                "TITLE" => {}
+                "LUT_1D_SIZE" | "LUT_1D_INPUT_RANGE" => {
+                    return Err(error("1d luts are not supported"))
+                }
+                "LUT_3D_SIZE" => {
+                    let value = words
+                        .get(1)
+                        .and_then(|value| value.parse::<u32>().ok())
+                        .filter(|&value| value >= 2)
+                        .ok_or_else(|| error("invalid lut size"))?;
+                    size = Some(value);
+                }
+                "DOMAIN_MIN" => domain_min = parse_floats(&words[1..])?.into(),
+                "DOMAIN_MAX" => domain_max = parse_floats(&words[1..])?.into(),
+                // the same domain for every channel
+                "LUT_3D_INPUT_RANGE" => {
+                    let [min, max] = match words[1..] {
+                        [min, max] => [min, max].map(|value| value.parse::<f32>()),
+                        _ => return Err(error("expected 2 numbers")),
+                    };
+                    let (Ok(min), Ok(max)) = (min, max) else {
+                        return Err(error("invalid number"));
+                    };
+                    domain_min = [min; 3].into();
+                    domain_max = [max; 3].into();
+                }
+                keyword
+                    if keyword.starts_with(|character: char| character.is_ascii_alphabetic()) =>
+                {
+                    return Err(error(&format!("unknown keyword {keyword:?}")));
+                }
+                _ => data.push(parse_floats(&words)?),
+            }
+        }
+
+        let size = size.ok_or(CubeLutError::MissingSize)?;
+        let expected = size as usize * size as usize * size as usize;
+        if data.len() != expected {
+            return Err(CubeLutError::WrongEntryCount {
+                expected,
+                found: data.len(),
+            });
+        }
+
+        Ok(Self {
+            size,
+            domain_min,
+            domain_max,
+            data,
+        })
+    }
+
+    pub fn size(&self) -> u32 {
+        self.size
+    }
+
+    pub fn domain_min(&self) -> Vector3 {
+        self.domain_min
+    }
+
+    pub fn domain_max(&self) -> Vector3 {
+        self.domain_max
+    }
+
+    pub(super) fn create_texture(
+        &self,
+        device: &wgpu::Device,
+        queue: &wgpu::Queue,
+    ) -> wgpu::Texture {
+        let size = wgpu::Extent3d {
+            width: self.size,
+            height: self.size,
+            depth_or_array_layers: self.size,
+        };
+        let texture = device.create_texture(&wgpu::TextureDescriptor {
+            label: Some("Color Grading LUT"),
+            size,
+            mip_level_count: 1,
+            sample_count: 1,
+            dimension: wgpu::TextureDimension::D3,
+            format: wgpu::TextureFormat::Rgba32Float,
+            usage: wgpu::TextureUsages::TEXTURE_BINDING | wgpu::TextureUsages::COPY_DST,
+            view_formats: &[],
+        });
+
+        let bytes = self
+            .data
+            .iter()
+            .flat_map(|&[r, g, b]| [r, g, b, 1.0])
+            .flat_map(f32::to_ne_bytes)
+            .collect::<Vec<u8>>();
+        queue.write_texture(
+            texture.as_image_copy(),
+            &bytes,
+            wgpu::ImageDataLayout {
+                offset: 0,
+                bytes_per_row: Some(self.size * 16),
+                rows_per_image: Some(self.size),
+            },
+            size,
+        );
+
+        texture
+    }
+}
+
+#[derive(Debug)]
+pub enum CubeLutError {
+    Io(io::Error),
+    Parse { line: usize, message: String },
+    MissingSize,
+    WrongEntryCount { expected: usize, found: usize },
+}
+
+impl fmt::Display for CubeLutError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            CubeLutError::Io(error) => write!(f, "{error}"),
+            CubeLutError::Parse { line, message } => write!(f, "line {line}: {message}"),
+            CubeLutError::MissingSize => write!(f, "the file has no LUT_3D_SIZE"),
+            CubeLutError::WrongEntryCount { expected, found } => {
+                write!(f, "expected {expected} lut entries but found {found}")
+            }
+        }
+    }
+}
+
+impl std::error::Error for CubeLutError {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            CubeLutError::Io(error) => Some(error),
+            _ => None,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const IDENTITY_CUBE: &str = "\
+TITLE \"identity\"
+# a comment
+LUT_3D_SIZE 2
+DOMAIN_MIN 0 0 0
+DOMAIN_MAX 1 2 4
+
+0 0 0
+1 0 0
+0 1 0
+1 1 0
+0 0 1
+1 0 1
+0 1 1
+1 1 1
+";
+
+    #[test]
+    fn parses_a_valid_file() {
+        let lut = Lut3d::parse_cube(IDENTITY_CUBE).unwrap();
+        assert_eq!(lut.size(), 2);
+        assert_eq!(lut.domain_max(), [1.0, 2.0, 4.0].into());
+        assert_eq!(lut.data, Lut3d::identity(2).data);
+    }
+
+    #[test]
+    fn the_input_range_sets_the_domain() {
+        let source = IDENTITY_CUBE
+            .replace("DOMAIN_MIN 0 0 0", "LUT_3D_INPUT_RANGE -0.5 2")
+            .replace("DOMAIN_MAX 1 2 4\n", "");
+        let lut = Lut3d::parse_cube(&source).unwrap();
+        assert_eq!(lut.domain_min(), [-0.5; 3].into());
+        assert_eq!(lut.domain_max(), [2.0; 3].into());
+    }
+
+    #[test]
+    fn rejects_a_wrong_entry_count() {
+        let source = IDENTITY_CUBE.replace("1 1 1\n", "");
+        assert!(matches!(
+            Lut3d::parse_cube(&source),
+            Err(CubeLutError::WrongEntryCount {
+                expected: 8,
+                found: 7
+            })
+        ));
+    }
+
+    #[test]
+    fn rejects_unknown_keywords() {
+        let source = IDENTITY_CUBE.replace("# a comment", "LUT_IN_VIDEO_RANGE");
+        match Lut3d::parse_cube(&source) {
+            Err(CubeLutError::Parse { line, message }) => {
+                assert_eq!(line, 2);
+                assert_eq!(message, "unknown keyword \"LUT_IN_VIDEO_RANGE\"");
+            }
+            result => panic!("expected a parse error, got {result:?}"),
+        }
+
+        let source = IDENTITY_CUBE.replace("LUT_3D_SIZE 2", "LUT_1D_SIZE 2");
+        assert!(matches!(
+            Lut3d::parse_cube(&source),
+            Err(CubeLutError::Parse { line: 3, .. })
+        ));
+    }
+}
@@ -0,0 +1,303 @@
+use bevy::{diagnostic::DiagnosticId, ecs::system::Resource};
+use std::sync::{Arc, Mutex};
+
+/// how long the gpu took for the frames that were rendered, timings arrive a few frames after the frame they belong to
+#[derive(Resource, Clone, Debug, Default)]
+pub struct RenderDiagnostics {
+    /// the gpu time in milliseconds of every render graph node that timed its pass, in the order the nodes ran,
+    /// empty when the adapter does not support timestamp queries
+    pub gpu_pass_times: Vec<(&'static str, f64)>,
+    /// the rays of a recent frame, `None` unless the [`CountRays`] resource exists
+    pub ray_statistics: Option<RayStatistics>,
+}
+
+/// the ids of the [`bevy::diagnostic`] diagnostics the renderer measures every frame,
+/// the frame time is measured by [`FrameTimeDiagnosticsPlugin`](bevy::diagnostic::FrameTimeDiagnosticsPlugin)
+impl RenderDiagnostics {
+    /// the number of primitives of every type, for example spheres
+    pub const PRIMITIVE_COUNT: DiagnosticId =
+        DiagnosticId::from_u128(38032776096201679517248466840187383440);
+    /// the bytes written to gpu buffers in a frame
+    pub const UPLOADED_BYTES: DiagnosticId =
+        DiagnosticId::from_u128(308979793106850430677656420176866001019);
+    /// how often the window surface was configured again since the renderer was created, for example because of a resize
+    pub const SURFACE_CONFIGURATIONS: DiagnosticId =
+        DiagnosticId::from_u128(310324205329609942400607420636451176076);
+    /// the [`RenderScale`](super::RenderScale) of the frame, which changes over time when it is adaptive
+    pub const RENDER_SCALE: DiagnosticId =
+        DiagnosticId::from_u128(89412755094471218553405726913610375882);
+}
+
+/// insert this resource to count the rays and intersection tests of the ray tracing shader, which makes it slower
+#[derive(Resource, Clone, Copy, Debug, Default)]
+pub struct CountRays;
+
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub struct RayStatistics {
+    /// the frame the rays were counted in, frames are counted from when the renderer was created
+    pub frame: u64,
+    /// one for every pixel
+    pub primary_rays: u64,
+    /// rays from a hit towards the sun
+    pub shadow_rays: u64,
+    /// rays that continue a path after a hit
+    pub bounce_rays: u64,
+    /// ray primitive intersection tests of all rays
+    pub intersection_tests: u64,
+    /// all rays divided by the gpu time of the ray tracing pass of the same frame,
+    /// `None` when the adapter does not support timestamp queries or the pass of that frame wasn't timed
+    pub rays_per_second: Option<f64>,
+    pub tests_per_ray: f64,
+}
+
+impl RayStatistics {
+    pub fn rays(&self) -> u64 {
+        self.primary_rays
+            .saturating_add(self.shadow_rays)
+            .saturating_add(self.bounce_rays)
+    }
+
+    // sets the rays per second when `ray_tracing_time` is the frame and the gpu time in milliseconds of the same frame
+    pub(super) fn combine_ray_tracing_time(&mut self, ray_tracing_time: Option<(u64, f64)>) {
+        if let Some((frame, milliseconds)) = ray_tracing_time {
+            if frame == self.frame && milliseconds > 0.0 {
+                self.rays_per_second = Some(self.rays() as f64 / (milliseconds / 1000.0));
+            }
+        }
+    }
+}
+
+// the result of mapping a readback buffer, `None` while it is being mapped
+type MapResult = Arc<Mutex<Option<Result<(), wgpu::BufferAsyncError>>>>;
+
+/// a buffer that gpu data is copied to, so it can be read without waiting for the gpu
+pub(super) struct ReadbackBuffer {
+    pub(super) buffer: wgpu::Buffer,
+    // the size that is being mapped
+    pending: Option<(u64, MapResult)>,
+}
+
+impl ReadbackBuffer {
+    pub(super) fn new(device: &wgpu::Device, label: &str, size: u64) -> Self {
+        Self {
+            buffer: device.create_buffer(&wgpu::BufferDescriptor {
+                label: Some(label),
+                size,
+                usage: wgpu::BufferUsages::COPY_DST | wgpu::BufferUsages::MAP_READ,
+                mapped_at_creation: false,
+            }),
+            pending: None,
+        }
+    }
+
+    // nothing can be copied to the buffer until the data that is being mapped was read
+    pub(super) fn is_pending(&self) -> bool {
+        self.pending.is_some()
+    }
+
+    // starts mapping the first `size` bytes, after the commands that copied them were submitted
+    pub(super) fn map(&mut self, size: u64) {
+        let result = MapResult::default();
+        self.buffer.slice(..size).map_async(wgpu::MapMode::Read, {
+            let result = result.clone();
+            move |map_result| *result.lock().unwrap() = Some(map_result)
+        });
+        self.pending = Some((size, result));
+    }
+
+    // the mapped data once it arrived, `Some(None)` if mapping failed
+    pub(super) fn read(&mut self) -> Option<Option<Vec<u8>>> {
+        let (size, result) = self.pending.as_ref()?;
+        let map_result = result.lock().unwrap().take()?;
+
+        let data = map_result.is_ok().then(|| {
+            let data = self.buffer.slice(..*size).get_mapped_range().to_vec();
+            self.buffer.unmap();
+            data
+        });
+        self.pending = None;
+        Some(data)
+    }
+}
+
+// the most passes that can be timed in a frame
+pub(super) const MAX_TIMED_PASSES: u32 = 32;
+
+/// measures how long render graph passes take on the gpu with timestamp queries
+pub(super) struct GpuTimer {
+    query_set: wgpu::QuerySet,
+    resolve_buffer: wgpu::Buffer,
+    readback_buffer: ReadbackBuffer,
+    // nanoseconds per timestamp tick
+    period: f64,
+    // the frame and the nodes of the timed passes whose timestamps are being read
+    frame: u64,
+    nodes: Vec<&'static str>,
+}
+
+impl GpuTimer {
+    /// returns `None` when the device was created without [`wgpu::Features::TIMESTAMP_QUERY`]
+    pub(super) fn new(device: &wgpu::Device, queue: &wgpu::Queue) -> Option<Self> {
+        if !device.features().contains(wgpu::Features::TIMESTAMP_QUERY) {
+            return None;
+        }
+
+        let size = (MAX_TIMED_PASSES * 2) as u64 * wgpu::QUERY_SIZE as u64;
+        Some(Self {
+            query_set: device.create_query_set(&wgpu::QuerySetDescriptor {
+                label: Some("Pass Timestamps"),
+                ty: wgpu::QueryType::Timestamp,
+                count: MAX_TIMED_PASSES * 2,
+            }),
+            resolve_buffer: device.create_buffer(&wgpu::BufferDescriptor {
+                label: Some("Pass Timestamp Resolve Buffer"),
+                size,
+                usage: wgpu::BufferUsages::QUERY_RESOLVE | wgpu::BufferUsages::COPY_SRC,
+                mapped_at_creation: false,
+            }),
+            readback_buffer: ReadbackBuffer::new(device, "Pass Timestamp Readback Buffer", size),
+            period: queue.get_timestamp_period() as f64,
+            frame: 0,
+            nodes: vec![],
+        })
+    }
+
+    /// the query set the passes of this frame write their timestamps to,
+    /// `None` while the timestamps of an earlier frame are still being read, so no frame is waited for
+    pub(super) fn query_set(&self) -> Option<&wgpu::QuerySet> {
+        (!self.readback_buffer.is_pending()).then_some(&self.query_set)
+    }
+
+    /// copies the timestamps of the passes of `nodes` to the readback buffer
+    pub(super) fn resolve(&self, encoder: &mut wgpu::CommandEncoder, nodes: &[&'static str]) {
+        if nodes.is_empty() {
+            return;
+        }
+        let count = nodes.len() as u32 * 2;
+        encoder.resolve_query_set(&self.query_set, 0..count, &self.resolve_buffer, 0);
+        encoder.copy_buffer_to_buffer(
+            &self.resolve_buffer,
+            0,
+            &self.readback_buffer.buffer,
+            0,
+            count as u64 * wgpu::QUERY_SIZE as u64,
+        );
+    }
+
+    /// starts reading the timestamps of `frame` back after the commands that resolved them were submitted
+    pub(super) fn map(&mut self, frame: u64, nodes: Vec<&'static str>) {
+        if nodes.is_empty() {
+            return;
+        }
+        self.readback_buffer
+            .map((nodes.len() * 2) as u64 * wgpu::QUERY_SIZE as u64);
+        self.frame = frame;
+        self.nodes = nodes;
+    }
+
+    /// the frame and the gpu time of every node in milliseconds once the timestamps arrived,
+    /// the times of passes of the same node are added
+    pub(super) fn read(&mut self) -> Option<(u64, Vec<(&'static str, f64)>)> {
+        let data = self.readback_buffer.read()?;
+
+        let mut times: Vec<(&'static str, f64)> = vec![];
+        let timestamps: Vec<u64> = data
+            .unwrap_or_default()
+            .chunks_exact(8)
+            .map(|bytes| u64::from_le_bytes(bytes.try_into().unwrap()))
+            .collect();
+        for (&node, pass) in self.nodes.iter().zip(timestamps.chunks_exact(2)) {
+            let milliseconds = pass[1].wrapping_sub(pass[0]) as f64 * self.period / 1_000_000.0;
+            match times.iter_mut().find(|(name, _)| *name == node) {
+                Some((_, time)) => *time += milliseconds,
+                None => times.push((node, milliseconds)),
+            }
+        }
+        Some((self.frame, times))
+    }
+}
+
+// primary, shadow and bounce rays and intersection tests
+const RAY_COUNTER_COUNT: u64 = 4;
+
+/// the atomic counters the ray tracing shader increments when rays are counted,
+/// every counter is a low and a high u32, which is a little endian u64
+pub(super) struct RayCounters {
+    buffer: wgpu::Buffer,
+    readback_buffer: ReadbackBuffer,
+    // the frame whose counts are being read
+    frame: u64,
+}
+
+impl RayCounters {
+    pub(super) fn new(device: &wgpu::Device) -> Self {
+        let size = RAY_COUNTER_COUNT * 8;
+        Self {
+            buffer: device.create_buffer(&wgpu::BufferDescriptor {
+                label: Some("Ray Counter Buffer"),
+                size,
+                usage: wgpu::BufferUsages::STORAGE
+                    | wgpu::BufferUsages::COPY_DST
+                    | wgpu::BufferUsages::COPY_SRC,
+                mapped_at_creation: false,
+            }),
+            readback_buffer: ReadbackBuffer::new(device, "Ray Counter Readback Buffer", size),
+            frame: 0,
+        }
+    }
+
+    pub(super) fn buffer(&self) -> &wgpu::Buffer {
+        &self.buffer
+    }
+
+    /// the counters can only be used while the counts of an earlier frame are not being read
+    pub(super) fn is_pending(&self) -> bool {
+        self.readback_buffer.is_pending()
+    }
+
+    /// sets the counters to zero before the ray tracing pass
+    pub(super) fn clear(&self, encoder: &mut wgpu::CommandEncoder) {
+        encoder.clear_buffer(&self.buffer, 0, None);
+    }
+
+    /// copies the counters to the readback buffer after the ray tracing pass
+    pub(super) fn copy(&self, encoder: &mut wgpu::CommandEncoder) {
+        encoder.copy_buffer_to_buffer(
+            &self.buffer,
+            0,
+            &self.readback_buffer.buffer,
+            0,
+            self.buffer.size(),
+        );
+    }
+
+    /// starts reading the counters of `frame` back after the commands that copied them were submitted
+    pub(super) fn map(&mut self, frame: u64) {
+        self.readback_buffer.map(self.buffer.size());
+        self.frame = frame;
+    }
+
+    /// the counts once they arrived, without the rays per second
+    pub(super) fn read(&mut self) -> Option<RayStatistics> {
+        let data = self.readback_buffer.read()??;
+        let counters: Vec<u64> = data
+            .chunks_exact(8)
+            .map(|bytes| u64::from_le_bytes(bytes.try_into().unwrap()))
+            .collect();
+
+        let statistics = RayStatistics {
+            frame: self.frame,
+            primary_rays: counters[0],
+            shadow_rays: counters[1],
+            bounce_rays: counters[2],
+            intersection_tests: counters[3],
+            rays_per_second: None,
+            tests_per_ray: 0.0,
+        };
+        Some(RayStatistics {
+            tests_per_ray: statistics.intersection_tests as f64
+                / (statistics.rays() as f64).max(1.0),
+            ..statistics
+        })
+    }
+}
@@ -0,0 +1,190 @@
+use crate::math::{Motor, Point, Vector3};
+use bevy::ecs::system::{ResMut, Resource, SystemParam};
+
+const CIRCLE_SEGMENTS: usize = 32;
+
+#[derive(Clone, Copy)]
+pub(super) struct GizmoLine {
+    pub(super) start: Vector3,
+    pub(super) end: Vector3,
+    pub(super) color: Vector3,
+}
+
+#[derive(Resource, Default)]
+pub(super) struct GizmoBuffer {
+    pub(super) lines: Vec<GizmoLine>,
+}
+
+pub(super) fn clear_gizmos(mut gizmo_buffer: ResMut<GizmoBuffer>) {
+    gizmo_buffer.lines.clear();
+}
+
+/// queues lines that get drawn over the ray traced image for a single frame
+#[derive(SystemParam)]
+pub struct Gizmos<'w> {
+    buffer: ResMut<'w, GizmoBuffer>,
+}
+
+impl Gizmos<'_> {
+    pub fn line(&mut self, start: Vector3, end: Vector3, color: Vector3) {
+        self.buffer.lines.push(GizmoLine { start, end, color });
+    }
+
+    /// draws the x, y and z axes of `motor` in red, green and blue
+    pub fn axes(&mut self, motor: Motor, length: f32) {
+        let origin = transform_point(
+            motor,
+            Vector3 {
+                x: 0.0,
+                y: 0.0,
+                z: 0.0,
+            },
+        );
+        for (axis, color) in [
+            (
+                Vector3 {
+                    x: length,
+                    y: 0.0,
+                    z: 0.0,
+                },
+                Vector3 {
+                    x: 1.0,
+                    y: 0.0,
+                    z: 0.0,
+                },
+            ),
+            (
+                Vector3 {
+                    x: 0.0,
+                    y: length,
+                    z: 0.0,
+                },
+                Vector3 {
+                    x: 0.0,
+                    y: 1.0,
+                    z: 0.0,
+                },
+            ),
+            (
+                Vector3 {
+                    x: 0.0,
+                    y: 0.0,
+                    z: length,
+                },
+                Vector3 {
+                    x: 0.0,
+                    y: 0.0,
+                    z: 1.0,
+                },
+            ),
+        ] {
+            self.line(origin, transform_point(motor, axis), color);
+        }
+    }
+
+    /// draws a circle around each of the local axes of `motor`
+    pub fn wire_sphere(&mut self, motor: Motor, radius: f32, color: Vector3) {
+        let circle_point = |index: usize| {
+            let angle = index as f32 / CIRCLE_SEGMENTS as f32 * std::f32::consts::TAU;
+            let (sin, cos) = angle.sin_cos();
+            (sin * radius, cos * radius)
+        };
+        for index in 0..CIRCLE_SEGMENTS {
+            let (a_sin, a_cos) = circle_point(index);
+            let (b_sin, b_cos) = circle_point(index + 1);
+            for (a, b) in [
+                (
+                    Vector3 {
+                        x: a_cos,
+                        y: a_sin,
+                        z: 0.0,
+                    },
+                    Vector3 {
+                        x: b_cos,
+                        y: b_sin,
+                        z: 0.0,
+                    },
+                ),
+                (
+                    Vector3 {
+                        x: a_cos,
+                        y: 0.0,
+                        z: a_sin,
+                    },
+                    Vector3 {
+                        x: b_cos,
+                        y: 0.0,
+                        z: b_sin,
+                    },
+                ),
+                (
+                    Vector3 {
+                        x: 0.0,
+                        y: a_cos,
+                        z: a_sin,
+                    },
+                    Vector3 {
+                        x: 0.0,
+                        y: b_cos,
+                        z: b_sin,
+                    },
+                ),
+            ] {
+                self.line(transform_point(motor, a), transform_point(motor, b), color);
+            }
+        }
+    }
+
+    /// draws a box centered on `motor` that extends `half_size` along each of its local axes
+    pub fn wire_box(&mut self, motor: Motor, half_size: Vector3, color: Vector3) {
+        let corner = |index: usize| {
+            transform_point(
+                motor,
+                Vector3 {
+                    x: if index & 1 == 0 {
+                        -half_size.x
+                    } else {
+                        half_size.x
+                    },
+                    y: if index & 2 == 0 {
+                        -half_size.y
+                    } else {
+                        half_size.y
+                    },
+                    z: if index & 4 == 0 {
+                        -half_size.z
+                    } else {
+                        half_size.z
+                    },
+                },
+            )
+        };
+        for index in 0..8 {
+            for axis in [1, 2, 4] {
+                if index & axis == 0 {
+                    self.line(corner(index), corner(index | axis), color);
+                }
+            }
+        }
+    }
+
+    pub fn aabb(&mut self, min: Vector3, max: Vector3, color: Vector3) {
+        self.wire_box(
+            Motor::translation(Vector3 {
+                x: (min.x + max.x) * 0.5,
+                y: (min.y + max.y) * 0.5,
+                z: (min.z + max.z) * 0.5,
+            }),
+            Vector3 {
+                x: (max.x - min.x) * 0.5,
+                y: (max.y - min.y) * 0.5,
+                z: (max.z - min.z) * 0.5,
+            },
+            color,
+        );
+    }
+}
+
+fn transform_point(motor: Motor, point: Vector3) -> Vector3 {
+    Point::from(point).transform(motor).into()
+}
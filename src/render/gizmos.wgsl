@@ -0,0 +1,72 @@
+#import math
+#import camera
+
+@group(0)
+@binding(0)
+var<uniform> camera: Camera;
+
+@group(1)
+@binding(0)
+var depth_texture: texture_2d<f32>;
+
+struct GizmoVertex {
+    position: vec3<f32>,
+    color: vec3<f32>,
+}
+
+struct GizmoVertices {
+    length: u32,
+    data: array<GizmoVertex>,
+}
+
+@group(2)
+@binding(0)
+var<storage, read> vertices: GizmoVertices;
+
+struct VertexOutput {
+    @builtin(position) clip_position: vec4<f32>,
+    @location(0) camera_position: vec3<f32>,
+    @location(1) color: vec3<f32>,
+    // the clip position before the perspective divide, so the depth texture can be read at its own size
+    @location(2) clip_xyw: vec3<f32>,
+}
+
+@vertex
+fn vertex(@builtin(vertex_index) vertex_index: u32) -> VertexOutput {
+    let vertex = vertices.data[vertex_index];
+
+    // the camera looks down +x with +y up and +z to the right, this is the inverse of the ray direction calculation in `ray_trace`
+    let camera_position = point_to_vec3(transform_point(vec3_to_point(vertex.position), inverse_motor(camera.transform)));
+
+    let size = textureDimensions(depth_texture);
+    let theta = tan(camera.v_fov / 2.0);
+    let aspect = f32(size.x) / f32(size.y);
+
+    var out: VertexOutput;
+    out.clip_position = vec4<f32>(
+        camera_position.z / (aspect * theta),
+        camera_position.y / theta,
+        0.0,
+        camera_position.x,
+    );
+    out.clip_xyw = out.clip_position.xyw;
+    out.camera_position = camera_position;
+    out.color = vertex.color;
+    return out;
+}
+
+// lines lying exactly on a surface would flicker without some tolerance
+const DEPTH_BIAS: f32 = 1.001;
+
+@fragment
+fn fragment(in: VertexOutput) -> @location(0) vec4<f32> {
+    // the depth texture is smaller than the main texture when the render scale is below 1
+    let ndc = in.clip_xyw.xy / in.clip_xyw.z;
+    let uv = vec2<f32>(ndc.x, -ndc.y) * 0.5 + 0.5;
+    let size = vec2<i32>(textureDimensions(depth_texture));
+    let depth = textureLoad(depth_texture, clamp(vec2<i32>(uv * vec2<f32>(size)), vec2<i32>(0), size - 1), 0).r;
+    if length(in.camera_position) > depth * DEPTH_BIAS {
+        discard;
+    }
+    return vec4<f32>(in.color, 1.0);
+}
@@ -0,0 +1,166 @@
+use crate::render::gpu_bytes_buffer::GpuBytesBuffer;
+use bevy::ecs::system::Resource;
+use encase::{internal::WriteInto, ArrayLength, ShaderSize, ShaderType, StorageBuffer};
+
+#[derive(ShaderType)]
+struct GpuArray<'a, T: ShaderType + ShaderSize + 'a> {
+    length: ArrayLength,
+    #[size(runtime)]
+    data: &'a [T],
+}
+
+// a struct is padded to its alignment, so its size is the stride of `T` in an array
+#[derive(ShaderType)]
+struct GpuArrayElement<T: ShaderType + ShaderSize> {
+    value: T,
+}
+
+/// a storage buffer with the layout `struct { length: u32, data: array<T> }` that is kept in sync with a set of components
+///
+/// the buffer grows when more values are written than fit into it and shrinks when they take up less than a quarter of it,
+/// and the bind group that holds it at binding 0 is recreated with it
+#[derive(Resource)]
+pub struct GpuArrayBuffer<T: ShaderType + ShaderSize + WriteInto + Send + Sync + 'static> {
+    // the values of the last `update`, they are kept to reuse the allocation
+    values: Vec<T>,
+    length: usize,
+    // the offset of the first value and the distance between values in bytes
+    data_offset: usize,
+    stride: usize,
+    buffer: GpuBytesBuffer,
+}
+
+impl<T: ShaderType + ShaderSize + WriteInto + Send + Sync + 'static> GpuArrayBuffer<T> {
+    pub fn new(device: &wgpu::Device, label: &'static str, visibility: wgpu::ShaderStages) -> Self {
+        let stride = GpuArrayElement::<T>::SHADER_SIZE.get() as usize;
+        // the minimum size makes room for a single value after the length
+        let data_offset = Self::min_binding_size().get() as usize - stride;
+        let mut buffer = GpuBytesBuffer::new(device, label, visibility, Self::min_binding_size());
+        buffer.bytes = vec![0; data_offset];
+
+        Self {
+            values: vec![],
+            length: 0,
+            data_offset,
+            stride,
+            buffer,
+        }
+    }
+
+    /// the smallest size of the buffer, which is the size of an empty array
+    pub fn min_binding_size() -> wgpu::BufferSize {
+        GpuArray::<'_, T>::min_size()
+    }
+
+    /// a read only storage buffer entry for bind group layouts that hold this buffer next to others
+    pub fn bind_group_layout_entry(
+        binding: u32,
+        visibility: wgpu::ShaderStages,
+    ) -> wgpu::BindGroupLayoutEntry {
+        GpuBytesBuffer::bind_group_layout_entry(binding, visibility, Self::min_binding_size())
+    }
+
+    /// replaces the array with `values` and uploads it, but only if any value changed or the number of values changed
+    ///
+    /// each value comes with whether the components it was made from changed, which is usually `is_changed` of the `Ref`s in a query,
+    /// returns whether the array was uploaded
+    pub fn update(
+        &mut self,
+        device: &wgpu::Device,
+        queue: &wgpu::Queue,
+        values: impl IntoIterator<Item = (T, bool)>,
+    ) -> bool {
+        self.buffer.begin_update();
+
+        let previous_length = self.length;
+        let mut values_changed = false;
+        self.values.clear();
+        for (value, changed) in values {
+            values_changed |= changed;
+            self.values.push(value);
+        }
+        self.length = self.values.len();
+
+        if !values_changed && self.length == previous_length {
+            return false;
+        }
+
+        self.buffer.bytes.clear();
+        StorageBuffer::new(&mut self.buffer.bytes)
+            .write(&GpuArray {
+                length: ArrayLength,
+                data: &self.values,
+            })
+            .unwrap();
+
+        let size = self.buffer.bytes.len();
+        self.buffer.upload(device, queue, std::iter::once(0..size));
+        true
+    }
+
+    /// overwrites single values and uploads only those, an index can also be the length of the array to add a value to the end
+    pub fn set(
+        &mut self,
+        device: &wgpu::Device,
+        queue: &wgpu::Queue,
+        values: impl IntoIterator<Item = (usize, T)>,
+    ) {
+        self.buffer.begin_update();
+
+        let previous_length = self.length;
+        let mut changed_ranges = vec![];
+        for (index, value) in values {
+            assert!(
+                index <= self.length,
+                "index {index} is past the end of an array with {} values",
+                self.length
+            );
+
+            let offset = self.data_offset + index * self.stride;
+            if index == self.length {
+                self.length += 1;
+                self.buffer.bytes.resize(offset + self.stride, 0);
+            }
+            StorageBuffer::new(&mut self.buffer.bytes[offset..offset + self.stride])
+                .write(&GpuArrayElement { value })
+                .unwrap();
+            changed_ranges.push(offset..offset + self.stride);
+        }
+        if self.length != previous_length {
+            self.buffer.bytes[..4].copy_from_slice(&(self.length as u32).to_le_bytes());
+            changed_ranges.push(0..4);
+        }
+
+        self.buffer.upload(device, queue, changed_ranges);
+    }
+
+    pub fn len(&self) -> usize {
+        self.length
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.length == 0
+    }
+
+    pub fn buffer(&self) -> &wgpu::Buffer {
+        self.buffer.buffer()
+    }
+
+    /// whether the last [`GpuArrayBuffer::update`] or [`GpuArrayBuffer::set`] replaced the buffer, so bind groups that hold it have to be recreated
+    pub fn buffer_recreated(&self) -> bool {
+        self.buffer.buffer_recreated()
+    }
+
+    /// how many bytes the last [`GpuArrayBuffer::update`] or [`GpuArrayBuffer::set`] wrote to the gpu
+    pub fn uploaded_bytes(&self) -> u64 {
+        self.buffer.uploaded_bytes()
+    }
+
+    pub fn bind_group_layout(&self) -> &wgpu::BindGroupLayout {
+        self.buffer.bind_group_layout()
+    }
+
+    pub fn bind_group(&self) -> &wgpu::BindGroup {
+        self.buffer.bind_group()
+    }
+}
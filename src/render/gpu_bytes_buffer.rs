@@ -0,0 +1,154 @@
+use std::ops::Range;
+
+/// a storage buffer that keeps a copy of its bytes, which [`GpuArrayBuffer`](super::GpuArrayBuffer) and [`GpuSlotBuffer`](super::GpuSlotBuffer) write their values into
+///
+/// the buffer is recreated when the bytes don't fit into it anymore or take up less than a quarter of it,
+/// and the bind group that holds it at binding 0 is recreated with it
+pub(super) struct GpuBytesBuffer {
+    label: &'static str,
+    // a copy of what is in the buffer
+    pub(super) bytes: Vec<u8>,
+    min_size: wgpu::BufferSize,
+    buffer: wgpu::Buffer,
+    bind_group_layout: wgpu::BindGroupLayout,
+    bind_group: wgpu::BindGroup,
+    buffer_recreated: bool,
+    uploaded_bytes: u64,
+}
+
+impl GpuBytesBuffer {
+    pub(super) fn new(
+        device: &wgpu::Device,
+        label: &'static str,
+        visibility: wgpu::ShaderStages,
+        min_size: wgpu::BufferSize,
+    ) -> Self {
+        let buffer = Self::create_buffer(device, label, min_size.get());
+        let bind_group_layout = device.create_bind_group_layout(&wgpu::BindGroupLayoutDescriptor {
+            label: Some(label),
+            entries: &[Self::bind_group_layout_entry(0, visibility, min_size)],
+        });
+        let bind_group = Self::create_bind_group(device, label, &bind_group_layout, &buffer);
+
+        Self {
+            label,
+            bytes: vec![],
+            min_size,
+            buffer,
+            bind_group_layout,
+            bind_group,
+            buffer_recreated: false,
+            uploaded_bytes: 0,
+        }
+    }
+
+    /// a read only storage buffer entry for bind group layouts that hold the buffer next to others
+    pub(super) fn bind_group_layout_entry(
+        binding: u32,
+        visibility: wgpu::ShaderStages,
+        min_size: wgpu::BufferSize,
+    ) -> wgpu::BindGroupLayoutEntry {
+        wgpu::BindGroupLayoutEntry {
+            binding,
+            visibility,
+            ty: wgpu::BindingType::Buffer {
+                ty: wgpu::BufferBindingType::Storage { read_only: true },
+                has_dynamic_offset: false,
+                min_binding_size: Some(min_size),
+            },
+            count: None,
+        }
+    }
+
+    /// forgets what the last update uploaded, before the bytes are changed
+    pub(super) fn begin_update(&mut self) {
+        self.buffer_recreated = false;
+        self.uploaded_bytes = 0;
+    }
+
+    /// uploads the `changed` ranges of the bytes, or all of them when the buffer had to be recreated to fit them
+    pub(super) fn upload(
+        &mut self,
+        device: &wgpu::Device,
+        queue: &wgpu::Queue,
+        changed: impl IntoIterator<Item = Range<usize>>,
+    ) {
+        let size = (self.bytes.len() as wgpu::BufferAddress).max(self.min_size.get());
+        let new_size = if size > self.buffer.size() {
+            // twice the size, so adding values one at a time does not recreate the buffer every time
+            size.max(self.buffer.size() * 2)
+        } else if size * 4 < self.buffer.size() {
+            // still twice the size, so removing and adding values doesn't recreate it every time either
+            size * 2
+        } else {
+            for range in changed {
+                self.write(queue, range);
+            }
+            return;
+        };
+
+        self.buffer = Self::create_buffer(device, self.label, new_size);
+        self.bind_group =
+            Self::create_bind_group(device, self.label, &self.bind_group_layout, &self.buffer);
+        self.buffer_recreated = true;
+        self.write(queue, 0..self.bytes.len());
+    }
+
+    pub(super) fn buffer(&self) -> &wgpu::Buffer {
+        &self.buffer
+    }
+
+    pub(super) fn buffer_recreated(&self) -> bool {
+        self.buffer_recreated
+    }
+
+    pub(super) fn uploaded_bytes(&self) -> u64 {
+        self.uploaded_bytes
+    }
+
+    pub(super) fn bind_group_layout(&self) -> &wgpu::BindGroupLayout {
+        &self.bind_group_layout
+    }
+
+    pub(super) fn bind_group(&self) -> &wgpu::BindGroup {
+        &self.bind_group
+    }
+
+    fn write(&mut self, queue: &wgpu::Queue, range: Range<usize>) {
+        queue.write_buffer(
+            &self.buffer,
+            range.start as wgpu::BufferAddress,
+            &self.bytes[range.clone()],
+        );
+        self.uploaded_bytes += range.len() as u64;
+    }
+
+    fn create_buffer(
+        device: &wgpu::Device,
+        label: &'static str,
+        size: wgpu::BufferAddress,
+    ) -> wgpu::Buffer {
+        device.create_buffer(&wgpu::BufferDescriptor {
+            label: Some(label),
+            size,
+            usage: wgpu::BufferUsages::COPY_DST | wgpu::BufferUsages::STORAGE,
+            mapped_at_creation: false,
+        })
+    }
+
+    fn create_bind_group(
+        device: &wgpu::Device,
+        label: &'static str,
+        bind_group_layout: &wgpu::BindGroupLayout,
+        buffer: &wgpu::Buffer,
+    ) -> wgpu::BindGroup {
+        device.create_bind_group(&wgpu::BindGroupDescriptor {
+            label: Some(label),
+            layout: bind_group_layout,
+            entries: &[wgpu::BindGroupEntry {
+                binding: 0,
+                resource: buffer.as_entire_binding(),
+            }],
+        })
+    }
+}
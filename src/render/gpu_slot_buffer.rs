@@ -0,0 +1,221 @@
+use crate::render::gpu_bytes_buffer::GpuBytesBuffer;
+use bevy::{
+    ecs::{entity::Entity, system::Resource},
+    utils::HashMap,
+};
+use encase::{internal::WriteInto, ArrayLength, ShaderSize, ShaderType, StorageBuffer};
+use std::marker::PhantomData;
+
+#[derive(ShaderType)]
+struct GpuSlot<T: ShaderType + ShaderSize> {
+    // 0 for free slots, which must be skipped by the shader
+    alive: u32,
+    value: T,
+}
+
+#[derive(ShaderType)]
+struct GpuSlots<'a, T: ShaderType + ShaderSize + 'a> {
+    length: ArrayLength,
+    #[size(runtime)]
+    data: &'a [GpuSlot<T>],
+}
+
+// the slots are only compacted when there are more than this many, so few entities don't move between slots
+const MIN_COMPACTED_SLOTS: usize = 64;
+
+/// a storage buffer with the layout `struct { length: u32, data: array<struct { alive: u32, value: T }> }` where every entity keeps the same slot while it exists
+///
+/// only the slots of entities that changed are uploaded, slots of entities that are gone are marked as not alive and reused for new entities,
+/// when less than a quarter of the slots are alive after an update, the next update moves the entities into the first slots and shrinks the buffer
+#[derive(Resource)]
+pub struct GpuSlotBuffer<T: ShaderType + ShaderSize + WriteInto + Send + Sync + 'static> {
+    entity_slots: HashMap<Entity, u32>,
+    slot_entities: Vec<Option<Entity>>,
+    free_slots: Vec<u32>,
+    // whether the entity in the slot was part of the current update
+    seen: Vec<bool>,
+    changed_slots: Vec<u32>,
+
+    // the offset of the first slot and the distance between slots in bytes
+    data_offset: usize,
+    slot_size: usize,
+
+    buffer: GpuBytesBuffer,
+    phantom: PhantomData<T>,
+}
+
+impl<T: ShaderType + ShaderSize + WriteInto + Send + Sync + 'static> GpuSlotBuffer<T> {
+    pub fn new(device: &wgpu::Device, label: &'static str, visibility: wgpu::ShaderStages) -> Self {
+        let slot_size = GpuSlot::<T>::SHADER_SIZE.get() as usize;
+        // the minimum size makes room for a single slot after the length
+        let data_offset = Self::min_binding_size().get() as usize - slot_size;
+        let mut buffer = GpuBytesBuffer::new(device, label, visibility, Self::min_binding_size());
+        // an empty header means a length of 0
+        buffer.bytes = vec![0; data_offset];
+
+        Self {
+            entity_slots: HashMap::default(),
+            slot_entities: vec![],
+            free_slots: vec![],
+            seen: vec![],
+            changed_slots: vec![],
+
+            data_offset,
+            slot_size,
+
+            buffer,
+            phantom: PhantomData,
+        }
+    }
+
+    pub fn min_binding_size() -> wgpu::BufferSize {
+        GpuSlots::<'_, T>::min_size()
+    }
+
+    /// a read only storage buffer entry for bind group layouts that hold this buffer next to others
+    pub fn bind_group_layout_entry(
+        binding: u32,
+        visibility: wgpu::ShaderStages,
+    ) -> wgpu::BindGroupLayoutEntry {
+        GpuBytesBuffer::bind_group_layout_entry(binding, visibility, Self::min_binding_size())
+    }
+
+    /// uploads the values of the entities that changed and frees the slots of the entities that are not part of `entities`
+    ///
+    /// each entity comes with whether its components changed, usually `is_changed` of the `Ref`s in a query,
+    /// and a function that creates its value, which is only called for changed entities and entities without a slot
+    pub fn update<F: FnOnce() -> T>(
+        &mut self,
+        device: &wgpu::Device,
+        queue: &wgpu::Queue,
+        entities: impl IntoIterator<Item = (Entity, bool, F)>,
+    ) {
+        self.buffer.begin_update();
+        self.changed_slots.clear();
+
+        let previous_slot_count = self.slot_entities.len();
+        if previous_slot_count > MIN_COMPACTED_SLOTS
+            && self.entity_slots.len() * 4 < previous_slot_count
+        {
+            // every entity gets a new slot, starting from the first one
+            self.entity_slots.clear();
+            self.slot_entities.clear();
+            self.free_slots.clear();
+            self.seen.clear();
+            self.buffer.bytes.truncate(self.data_offset);
+        }
+        self.seen.iter_mut().for_each(|seen| *seen = false);
+
+        for (entity, changed, value) in entities {
+            let slot = match self.entity_slots.get(&entity) {
+                Some(&slot) if !changed => {
+                    self.seen[slot as usize] = true;
+                    continue;
+                }
+                Some(&slot) => slot,
+                None => {
+                    let slot = self.free_slots.pop().unwrap_or_else(|| {
+                        self.slot_entities.push(None);
+                        self.seen.push(false);
+                        self.slot_entities.len() as u32 - 1
+                    });
+                    self.entity_slots.insert(entity, slot);
+                    self.slot_entities[slot as usize] = Some(entity);
+                    slot
+                }
+            };
+            self.seen[slot as usize] = true;
+            self.write_slot(slot, value());
+        }
+
+        for slot in 0..self.slot_entities.len() as u32 {
+            if self.seen[slot as usize] {
+                continue;
+            }
+            if let Some(entity) = self.slot_entities[slot as usize].take() {
+                self.entity_slots.remove(&entity);
+                self.free_slots.push(slot);
+                // the value of a dead slot is never read, so only the alive flag has to be cleared
+                let offset = self.slot_offset(slot);
+                self.buffer.bytes[offset..offset + 4].copy_from_slice(&0u32.to_le_bytes());
+                self.changed_slots.push(slot);
+            }
+        }
+
+        let slot_count = self.slot_entities.len();
+        let mut changed_ranges = vec![];
+        if slot_count != previous_slot_count {
+            self.buffer.bytes[..4].copy_from_slice(&(slot_count as u32).to_le_bytes());
+            changed_ranges.push(0..4);
+        }
+
+        // neighbouring slots are uploaded together
+        self.changed_slots.sort_unstable();
+        let mut index = 0;
+        while index < self.changed_slots.len() {
+            let first = self.changed_slots[index];
+            let mut last = first;
+            while index + 1 < self.changed_slots.len() && self.changed_slots[index + 1] <= last + 1
+            {
+                index += 1;
+                last = self.changed_slots[index];
+            }
+            index += 1;
+
+            changed_ranges.push(self.slot_offset(first)..self.slot_offset(last) + self.slot_size);
+        }
+        self.buffer.upload(device, queue, changed_ranges);
+    }
+
+    /// the number of slots including the free ones
+    pub fn len(&self) -> usize {
+        self.slot_entities.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.slot_entities.is_empty()
+    }
+
+    /// the slot of an entity, which only changes when the slots are compacted
+    pub fn slot(&self, entity: Entity) -> Option<u32> {
+        self.entity_slots.get(&entity).copied()
+    }
+
+    pub fn buffer(&self) -> &wgpu::Buffer {
+        self.buffer.buffer()
+    }
+
+    /// whether the last [`GpuSlotBuffer::update`] replaced the buffer, so bind groups that hold it have to be recreated
+    pub fn buffer_recreated(&self) -> bool {
+        self.buffer.buffer_recreated()
+    }
+
+    /// how many bytes the last [`GpuSlotBuffer::update`] wrote to the gpu
+    pub fn uploaded_bytes(&self) -> u64 {
+        self.buffer.uploaded_bytes()
+    }
+
+    pub fn bind_group_layout(&self) -> &wgpu::BindGroupLayout {
+        self.buffer.bind_group_layout()
+    }
+
+    pub fn bind_group(&self) -> &wgpu::BindGroup {
+        self.buffer.bind_group()
+    }
+
+    fn slot_offset(&self, slot: u32) -> usize {
+        self.data_offset + slot as usize * self.slot_size
+    }
+
+    fn write_slot(&mut self, slot: u32, value: T) {
+        let offset = self.slot_offset(slot);
+        if self.buffer.bytes.len() < offset + self.slot_size {
+            self.buffer.bytes.resize(offset + self.slot_size, 0);
+        }
+
+        let mut slot_bytes =
+            StorageBuffer::new(&mut self.buffer.bytes[offset..offset + self.slot_size]);
+        slot_bytes.write(&GpuSlot { alive: 1, value }).unwrap();
+        self.changed_slots.push(slot);
+    }
+}
@@ -0,0 +1,68 @@
+use crate::math::Vector3;
+use bevy::ecs::{component::Component, system::Resource};
+
+#[derive(Clone, Copy)]
+pub struct Material {
+    pub color: Vector3,
+    /// light given off by the surface, values above 1 will make the surface glow when [`Bloom`](super::Bloom) is enabled
+    pub emission: Vector3,
+}
+
+/// refers to a material in [`Materials`], every entity with the same handle shares the material
+#[derive(Component, Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub struct MaterialHandle(u32);
+
+impl MaterialHandle {
+    pub fn index(self) -> u32 {
+        self.0
+    }
+}
+
+/// the materials of the scene, they are uploaded to the gpu once and again only when they are changed
+#[derive(Resource, Default)]
+pub struct Materials {
+    materials: Vec<Material>,
+    // the materials that were added or changed since they were last uploaded
+    changed: Vec<u32>,
+}
+
+impl Materials {
+    pub fn add(&mut self, material: Material) -> MaterialHandle {
+        let handle = MaterialHandle(self.materials.len() as u32);
+        self.materials.push(material);
+        self.changed.push(handle.0);
+        handle
+    }
+
+    pub fn get(&self, handle: MaterialHandle) -> Option<&Material> {
+        self.materials.get(handle.0 as usize)
+    }
+
+    pub fn get_mut(&mut self, handle: MaterialHandle) -> Option<&mut Material> {
+        let material = self.materials.get_mut(handle.0 as usize)?;
+        self.changed.push(handle.0);
+        Some(material)
+    }
+
+    pub fn len(&self) -> usize {
+        self.materials.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.materials.is_empty()
+    }
+
+    // makes every material upload again, for a new material buffer
+    pub(super) fn mark_all_changed(&mut self) {
+        self.changed = (0..self.materials.len() as u32).collect();
+    }
+
+    /// the materials that changed since the last call, in the order of their handles
+    pub(super) fn drain_changed(&mut self) -> impl Iterator<Item = (u32, &Material)> {
+        self.changed.sort_unstable();
+        self.changed.dedup();
+        self.changed
+            .drain(..)
+            .map(|index| (index, &self.materials[index as usize]))
+    }
+}
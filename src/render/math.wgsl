@@ -0,0 +1,106 @@
+// projective geometric algebra types and functions, these have to match `crate::math`
+
+struct Point {
+    e012: f32,
+    e013: f32,
+    e023: f32,
+    e123: f32,
+}
+
+fn vec3_to_point(v: vec3<f32>) -> Point {
+    var result: Point;
+    result.e012 = v.z;
+    result.e013 = -v.y;
+    result.e023 = v.x;
+    result.e123 = 1.0;
+    return result;
+}
+
+fn point_to_vec3(p: Point) -> vec3<f32> {
+    return vec3<f32>(
+        p.e023 / p.e123,
+        -p.e013 / p.e123,
+        p.e012 / p.e123,
+    );
+}
+
+struct Motor {
+    s: f32,
+    e12: f32,
+    e13: f32,
+    e23: f32,
+    e01: f32,
+    e02: f32,
+    e03: f32,
+    e0123: f32,
+}
+
+fn rotation_part_of_motor(motor: Motor) -> Motor {
+    var result = motor;
+    result.e01 = 0.0;
+    result.e02 = 0.0;
+    result.e03 = 0.0;
+    result.e0123 = 0.0;
+    return result;
+}
+
+fn transform_point(point: Point, motor: Motor) -> Point {
+    let a = motor.s;
+    let b = motor.e12;
+    let c = motor.e13;
+    let d = motor.e23;
+    let e = motor.e01;
+    let f = motor.e02;
+    let g = motor.e03;
+    let h = motor.e0123;
+    let i = point.e012;
+    let j = point.e013;
+    let k = point.e023;
+    let l = point.e123;
+
+    var result: Point;
+    result.e012 = -2.0 * a * d * j + -2.0 * a * g * l + 1.0 * a * a * i + 2.0 * a * c * k + -1.0 * d * d * i + -2.0 * d * f * l + 2.0 * b * d * k + -2.0 * b * h * l + -2.0 * c * e * l + 1.0 * b * b * i + 2.0 * b * c * j + -1.0 * c * c * i;
+    result.e013 = -2.0 * a * b * k + -1.0 * b * b * j + 2.0 * b * c * i + 2.0 * b * e * l + 1.0 * a * a * j + 2.0 * a * d * i + 2.0 * a * f * l + -2.0 * c * h * l + -2.0 * d * g * l + -1.0 * d * d * j + 2.0 * c * d * k + 1.0 * c * c * j;
+    result.e023 = -2.0 * a * c * i + -2.0 * a * e * l + 1.0 * a * a * k + 2.0 * a * b * j + -1.0 * c * c * k + 2.0 * c * d * j + 2.0 * c * g * l + -2.0 * d * h * l + 2.0 * b * f * l + -1.0 * b * b * k + 2.0 * b * d * i + 1.0 * d * d * k;
+    result.e123 = a * a * l + b * b * l + c * c * l + d * d * l;
+    return result;
+}
+
+// blends two motors and normalizes the result, which is close to the screw motion between them when they are close together
+fn interpolate_motor(a: Motor, b: Motor, t: f32) -> Motor {
+    // a motor and its negation are the same transformation, so the blend goes the shorter way
+    let rotor_dot = a.s * b.s + a.e12 * b.e12 + a.e13 * b.e13 + a.e23 * b.e23;
+    let sign = select(1.0, -1.0, rotor_dot < 0.0);
+
+    var result: Motor;
+    result.s = mix(a.s, b.s * sign, t);
+    result.e12 = mix(a.e12, b.e12 * sign, t);
+    result.e13 = mix(a.e13, b.e13 * sign, t);
+    result.e23 = mix(a.e23, b.e23 * sign, t);
+    result.e01 = mix(a.e01, b.e01 * sign, t);
+    result.e02 = mix(a.e02, b.e02 * sign, t);
+    result.e03 = mix(a.e03, b.e03 * sign, t);
+    result.e0123 = mix(a.e0123, b.e0123 * sign, t);
+
+    let magnitude = sqrt(result.s * result.s + result.e12 * result.e12 + result.e13 * result.e13 + result.e23 * result.e23);
+    result.s /= magnitude;
+    result.e12 /= magnitude;
+    result.e13 /= magnitude;
+    result.e23 /= magnitude;
+    result.e01 /= magnitude;
+    result.e02 /= magnitude;
+    result.e03 /= magnitude;
+    result.e0123 /= magnitude;
+    return result;
+}
+
+fn inverse_motor(motor: Motor) -> Motor {
+    var result = motor;
+    result.e12 = -motor.e12;
+    result.e13 = -motor.e13;
+    result.e23 = -motor.e23;
+    result.e01 = -motor.e01;
+    result.e02 = -motor.e02;
+    result.e03 = -motor.e03;
+    return result;
+}
@@ -0,0 +1,84 @@
+use crate::render::{RenderSchedule, TiledRender, TiledRenderError};
+use bevy::{
+    ecs::{system::Command, world::World},
+    log::{error, info},
+};
+use std::{fmt, path::PathBuf};
+
+/// renders a single frame of the main camera at its own resolution and sample count, waits until it is done and saves it,
+/// add it with `commands.add(RenderStill { .. })`
+///
+/// the scene is uploaded by running the [`RenderSchedule`] first, which also draws a frame to the window
+#[derive(Clone, Debug)]
+pub struct RenderStill {
+    /// the resolution, sample count and tile size of the image, independent of the window
+    pub tiled_render: TiledRender,
+    /// an 8 bit image with the post processing of the main camera, like the window shows it
+    pub png_path: Option<PathBuf>,
+    /// the linear hdr image without post processing
+    pub exr_path: Option<PathBuf>,
+}
+
+impl RenderStill {
+    /// renders the image and saves it to every path that is set, logging the progress
+    pub fn render(&self, world: &mut World) -> Result<(), RenderStillError> {
+        world
+            .try_run_schedule(RenderSchedule)
+            .map_err(|_| RenderStillError::Render(TiledRenderError::Unavailable))?;
+
+        let mut logged_percent = 0;
+        let image = self
+            .tiled_render
+            .render(world, |progress| {
+                let percent = (progress.fraction() * 100.0) as u32;
+                if percent >= logged_percent + 10 || progress.finished_tiles == progress.tile_count
+                {
+                    info!(
+                        "rendered {}/{} tiles of the still",
+                        progress.finished_tiles, progress.tile_count
+                    );
+                    logged_percent = percent;
+                }
+            })
+            .map_err(RenderStillError::Render)?;
+
+        if let Some(png_path) = &self.png_path {
+            image
+                .post_process(world)
+                .map_err(RenderStillError::Render)?
+                .save_with_format(png_path, image::ImageFormat::Png)
+                .map_err(RenderStillError::Save)?;
+            info!("saved the still to {}", png_path.display());
+        }
+        if let Some(exr_path) = &self.exr_path {
+            image.save_exr(exr_path).map_err(RenderStillError::Save)?;
+            info!("saved the still to {}", exr_path.display());
+        }
+        Ok(())
+    }
+}
+
+impl Command for RenderStill {
+    fn apply(self, world: &mut World) {
+        if let Err(error) = self.render(world) {
+            error!("failed to render a still: {error}");
+        }
+    }
+}
+
+#[derive(Debug)]
+pub enum RenderStillError {
+    Render(TiledRenderError),
+    Save(image::ImageError),
+}
+
+impl fmt::Display for RenderStillError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            RenderStillError::Render(error) => write!(f, "{error}"),
+            RenderStillError::Save(error) => write!(f, "failed to save the image: {error}"),
+        }
+    }
+}
+
+impl std::error::Error for RenderStillError {}
@@ -0,0 +1,72 @@
+@group(0)
+@binding(0)
+var font_atlas: texture_2d<f32>;
+
+struct OverlayQuad {
+    position: vec2<f32>,
+    size: vec2<f32>,
+    color: vec3<f32>,
+    glyph: u32,
+}
+
+struct OverlayQuads {
+    screen_size: vec2<f32>,
+    length: u32,
+    data: array<OverlayQuad>,
+}
+
+@group(1)
+@binding(0)
+var<storage, read> quads: OverlayQuads;
+
+const SOLID_GLYPH: u32 = 0xFFFFFFFFu;
+const GLYPH_SIZE: u32 = 8u;
+const ATLAS_COLUMNS: u32 = 16u;
+
+struct VertexOutput {
+    @builtin(position) clip_position: vec4<f32>,
+    @location(0) uv: vec2<f32>,
+    @location(1) color: vec3<f32>,
+    @location(2) @interpolate(flat) glyph: u32,
+}
+
+@vertex
+fn vertex(
+    @builtin(vertex_index) vertex_index: u32,
+    @builtin(instance_index) instance_index: u32,
+) -> VertexOutput {
+    let quad = quads.data[instance_index];
+
+    var corners = array<vec2<f32>, 6>(
+        vec2<f32>(0.0, 0.0),
+        vec2<f32>(1.0, 0.0),
+        vec2<f32>(0.0, 1.0),
+        vec2<f32>(0.0, 1.0),
+        vec2<f32>(1.0, 0.0),
+        vec2<f32>(1.0, 1.0),
+    );
+    let corner = corners[vertex_index];
+
+    // quads are positioned in pixels from the top left of the screen
+    let pixel = quad.position + corner * quad.size;
+    let normalized = pixel / quads.screen_size * 2.0 - 1.0;
+
+    var out: VertexOutput;
+    out.clip_position = vec4<f32>(normalized.x, -normalized.y, 0.0, 1.0);
+    out.uv = corner;
+    out.color = quad.color;
+    out.glyph = quad.glyph;
+    return out;
+}
+
+@fragment
+fn fragment(in: VertexOutput) -> @location(0) vec4<f32> {
+    if in.glyph != SOLID_GLYPH {
+        let glyph_pixel = min(vec2<u32>(in.uv * f32(GLYPH_SIZE)), vec2<u32>(GLYPH_SIZE - 1u));
+        let atlas_pixel = vec2<u32>(in.glyph % ATLAS_COLUMNS, in.glyph / ATLAS_COLUMNS) * GLYPH_SIZE + glyph_pixel;
+        if textureLoad(font_atlas, atlas_pixel, 0).r < 0.5 {
+            discard;
+        }
+    }
+    return vec4<f32>(in.color, 1.0);
+}
@@ -0,0 +1,484 @@
+use crate::render::{ColorGrading, UpscaleFilter};
+use bevy::ecs::component::Component;
+
+pub(super) const HDR_TEXTURE_FORMAT: wgpu::TextureFormat = wgpu::TextureFormat::Rgba16Float;
+/// the format the post processing resolves into, which is copied to the surface as it is
+pub(super) const MAIN_TEXTURE_FORMAT: wgpu::TextureFormat = wgpu::TextureFormat::Rgba8Unorm;
+
+// the most times the bloom texture is halved before it gets blurred back up
+const BLOOM_LEVELS: u32 = 6;
+
+/// the post processing effects applied to the image of the camera it is attached to, every effect that is `None` is skipped
+#[derive(Component, Clone, Copy, Default)]
+pub struct PostProcess {
+    pub bloom: Option<Bloom>,
+    pub chromatic_aberration: Option<ChromaticAberration>,
+    pub vignette: Option<Vignette>,
+    pub film_grain: Option<FilmGrain>,
+}
+
+#[derive(Clone, Copy)]
+pub struct Bloom {
+    /// how bright a pixel has to be before it starts to glow
+    pub threshold: f32,
+    pub intensity: f32,
+}
+
+impl Default for Bloom {
+    fn default() -> Self {
+        Self {
+            threshold: 1.0,
+            intensity: 0.3,
+        }
+    }
+}
+
+#[derive(Clone, Copy)]
+pub struct ChromaticAberration {
+    /// how far the red and blue channels are shifted at the edge of the screen, as a fraction of the screen size
+    pub intensity: f32,
+}
+
+impl Default for ChromaticAberration {
+    fn default() -> Self {
+        Self { intensity: 0.01 }
+    }
+}
+
+#[derive(Clone, Copy)]
+pub struct Vignette {
+    pub intensity: f32,
+    /// the distance from the center of the screen where the darkening starts, 1 is the middle of the edges
+    pub radius: f32,
+    pub smoothness: f32,
+}
+
+impl Default for Vignette {
+    fn default() -> Self {
+        Self {
+            intensity: 0.5,
+            radius: 0.75,
+            smoothness: 0.5,
+        }
+    }
+}
+
+#[derive(Clone, Copy)]
+pub struct FilmGrain {
+    pub intensity: f32,
+}
+
+impl Default for FilmGrain {
+    fn default() -> Self {
+        Self { intensity: 0.05 }
+    }
+}
+
+pub(super) struct PostProcessPipelines {
+    bloom_threshold: wgpu::ComputePipeline,
+    bloom_downsample: wgpu::ComputePipeline,
+    bloom_upsample: wgpu::ComputePipeline,
+    bloom_composite: wgpu::ComputePipeline,
+    chromatic_aberration: wgpu::ComputePipeline,
+    color_grading: wgpu::ComputePipeline,
+    lut: wgpu::ComputePipeline,
+    vignette: wgpu::ComputePipeline,
+    film_grain: wgpu::ComputePipeline,
+    resolve: wgpu::ComputePipeline,
+    resolve_edge_aware: wgpu::ComputePipeline,
+
+    bind_group_layout: wgpu::BindGroupLayout,
+    resolve_bind_group_layout: wgpu::BindGroupLayout,
+    sampler: wgpu::Sampler,
+}
+
+impl PostProcessPipelines {
+    pub(super) fn new(
+        device: &wgpu::Device,
+        settings_bind_group_layout: &wgpu::BindGroupLayout,
+    ) -> Self {
+        let source_entries = [
+            wgpu::BindGroupLayoutEntry {
+                binding: 0,
+                visibility: wgpu::ShaderStages::COMPUTE,
+                ty: wgpu::BindingType::Texture {
+                    sample_type: wgpu::TextureSampleType::Float { filterable: true },
+                    view_dimension: wgpu::TextureViewDimension::D2,
+                    multisampled: false,
+                },
+                count: None,
+            },
+            wgpu::BindGroupLayoutEntry {
+                binding: 1,
+                visibility: wgpu::ShaderStages::COMPUTE,
+                ty: wgpu::BindingType::Texture {
+                    sample_type: wgpu::TextureSampleType::Float { filterable: true },
+                    view_dimension: wgpu::TextureViewDimension::D2,
+                    multisampled: false,
+                },
+                count: None,
+            },
+            wgpu::BindGroupLayoutEntry {
+                binding: 2,
+                visibility: wgpu::ShaderStages::COMPUTE,
+                ty: wgpu::BindingType::Sampler(wgpu::SamplerBindingType::Filtering),
+                count: None,
+            },
+        ];
+
+        let bind_group_layout = device.create_bind_group_layout(&wgpu::BindGroupLayoutDescriptor {
+            label: Some("Post Process Bind Group Layout"),
+            entries: &[
+                source_entries[0],
+                source_entries[1],
+                source_entries[2],
+                wgpu::BindGroupLayoutEntry {
+                    binding: 3,
+                    visibility: wgpu::ShaderStages::COMPUTE,
+                    ty: wgpu::BindingType::StorageTexture {
+                        access: wgpu::StorageTextureAccess::WriteOnly,
+                        format: HDR_TEXTURE_FORMAT,
+                        view_dimension: wgpu::TextureViewDimension::D2,
+                    },
+                    count: None,
+                },
+            ],
+        });
+
+        let resolve_bind_group_layout =
+            device.create_bind_group_layout(&wgpu::BindGroupLayoutDescriptor {
+                label: Some("Post Process Resolve Bind Group Layout"),
+                entries: &[
+                    source_entries[0],
+                    source_entries[1],
+                    source_entries[2],
+                    wgpu::BindGroupLayoutEntry {
+                        binding: 4,
+                        visibility: wgpu::ShaderStages::COMPUTE,
+                        ty: wgpu::BindingType::StorageTexture {
+                            access: wgpu::StorageTextureAccess::WriteOnly,
+                            format: MAIN_TEXTURE_FORMAT,
+                            view_dimension: wgpu::TextureViewDimension::D2,
+                        },
+                        count: None,
+                    },
+                ],
+            });
+
+        let pipeline_layout = device.create_pipeline_layout(&wgpu::PipelineLayoutDescriptor {
+            label: Some("Post Process Pipeline Layout"),
+            bind_group_layouts: &[&bind_group_layout, settings_bind_group_layout],
+            push_constant_ranges: &[],
+        });
+
+        let resolve_pipeline_layout =
+            device.create_pipeline_layout(&wgpu::PipelineLayoutDescriptor {
+                label: Some("Post Process Resolve Pipeline Layout"),
+                bind_group_layouts: &[&resolve_bind_group_layout, settings_bind_group_layout],
+                push_constant_ranges: &[],
+            });
+
+        let shader = device.create_shader_module(wgpu::include_wgsl!("./post_process.wgsl"));
+        let create_pipeline = |layout, entry_point| {
+            device.create_compute_pipeline(&wgpu::ComputePipelineDescriptor {
+                label: Some(entry_point),
+                layout: Some(layout),
+                module: &shader,
+                entry_point,
+            })
+        };
+
+        let sampler = device.create_sampler(&wgpu::SamplerDescriptor {
+            label: Some("Post Process Sampler"),
+            address_mode_u: wgpu::AddressMode::ClampToEdge,
+            address_mode_v: wgpu::AddressMode::ClampToEdge,
+            address_mode_w: wgpu::AddressMode::ClampToEdge,
+            mag_filter: wgpu::FilterMode::Linear,
+            min_filter: wgpu::FilterMode::Linear,
+            mipmap_filter: wgpu::FilterMode::Nearest,
+            ..Default::default()
+        });
+
+        Self {
+            bloom_threshold: create_pipeline(&pipeline_layout, "bloom_threshold"),
+            bloom_downsample: create_pipeline(&pipeline_layout, "bloom_downsample"),
+            bloom_upsample: create_pipeline(&pipeline_layout, "bloom_upsample"),
+            bloom_composite: create_pipeline(&pipeline_layout, "bloom_composite"),
+            chromatic_aberration: create_pipeline(&pipeline_layout, "chromatic_aberration"),
+            color_grading: create_pipeline(&pipeline_layout, "color_grading"),
+            lut: create_pipeline(&pipeline_layout, "lut"),
+            vignette: create_pipeline(&pipeline_layout, "vignette"),
+            film_grain: create_pipeline(&pipeline_layout, "film_grain"),
+            resolve: create_pipeline(&resolve_pipeline_layout, "resolve"),
+            resolve_edge_aware: create_pipeline(&resolve_pipeline_layout, "resolve_edge_aware"),
+
+            bind_group_layout,
+            resolve_bind_group_layout,
+            sampler,
+        }
+    }
+}
+
+/// the textures used by the post processing passes, they have the size of the hdr texture
+///
+/// the ray tracer writes into the hdr texture of the render graph, every effect then reads one hdr texture and writes the other,
+/// and the resolve pass finally writes whichever one is the latest into the main texture, upscaling it when the sizes differ
+pub(super) struct PostProcessTargets {
+    size: (u32, u32),
+    main_size: (u32, u32),
+
+    bloom_threshold_bind_group: wgpu::BindGroup,
+    // the bind group at index `i` writes bloom level `i + 1`
+    bloom_downsample_bind_groups: Vec<wgpu::BindGroup>,
+    // the bind group at index `i` writes bloom level `i`, they are run from the smallest level up
+    bloom_upsample_bind_groups: Vec<wgpu::BindGroup>,
+    bloom_composite_bind_group: wgpu::BindGroup,
+    bloom_sizes: Vec<(u32, u32)>,
+
+    // the bind group at index `i` reads hdr texture `i` and writes the other one
+    bind_groups: [wgpu::BindGroup; 2],
+    resolve_bind_groups: [wgpu::BindGroup; 2],
+}
+
+impl PostProcessTargets {
+    pub(super) fn new(
+        device: &wgpu::Device,
+        pipelines: &PostProcessPipelines,
+        hdr_texture: &wgpu::Texture,
+        main_texture: &wgpu::Texture,
+    ) -> Self {
+        let (width, height) = (hdr_texture.width(), hdr_texture.height());
+
+        let create_texture = |label, width, height, mip_level_count| {
+            device.create_texture(&wgpu::TextureDescriptor {
+                label: Some(label),
+                size: wgpu::Extent3d {
+                    width,
+                    height,
+                    depth_or_array_layers: 1,
+                },
+                mip_level_count,
+                sample_count: 1,
+                dimension: wgpu::TextureDimension::D2,
+                format: HDR_TEXTURE_FORMAT,
+                usage: wgpu::TextureUsages::STORAGE_BINDING | wgpu::TextureUsages::TEXTURE_BINDING,
+                view_formats: &[],
+            })
+        };
+        let mip_view = |texture: &wgpu::Texture, mip_level| {
+            texture.create_view(&wgpu::TextureViewDescriptor {
+                base_mip_level: mip_level,
+                mip_level_count: Some(1),
+                ..Default::default()
+            })
+        };
+        let create_bind_group = |source: &wgpu::TextureView,
+                                 secondary: &wgpu::TextureView,
+                                 output: &wgpu::TextureView| {
+            device.create_bind_group(&wgpu::BindGroupDescriptor {
+                label: Some("Post Process Bind Group"),
+                layout: &pipelines.bind_group_layout,
+                entries: &[
+                    wgpu::BindGroupEntry {
+                        binding: 0,
+                        resource: wgpu::BindingResource::TextureView(source),
+                    },
+                    wgpu::BindGroupEntry {
+                        binding: 1,
+                        resource: wgpu::BindingResource::TextureView(secondary),
+                    },
+                    wgpu::BindGroupEntry {
+                        binding: 2,
+                        resource: wgpu::BindingResource::Sampler(&pipelines.sampler),
+                    },
+                    wgpu::BindGroupEntry {
+                        binding: 3,
+                        resource: wgpu::BindingResource::TextureView(output),
+                    },
+                ],
+            })
+        };
+
+        let hdr_views = [
+            hdr_texture,
+            &create_texture("HDR Texture", width, height, 1),
+        ]
+        .map(|texture| texture.create_view(&wgpu::TextureViewDescriptor::default()));
+
+        let (bloom_width, bloom_height) = ((width / 2).max(1), (height / 2).max(1));
+        let bloom_levels = BLOOM_LEVELS.min(bloom_width.min(bloom_height).ilog2() + 1);
+        let bloom_sizes = (0..bloom_levels)
+            .map(|level| {
+                (
+                    (bloom_width >> level).max(1),
+                    (bloom_height >> level).max(1),
+                )
+            })
+            .collect::<Vec<_>>();
+        let bloom_down_texture = create_texture(
+            "Bloom Down Texture",
+            bloom_width,
+            bloom_height,
+            bloom_levels,
+        );
+        let bloom_up_texture =
+            create_texture("Bloom Up Texture", bloom_width, bloom_height, bloom_levels);
+        let bloom_down_views = (0..bloom_levels)
+            .map(|level| mip_view(&bloom_down_texture, level))
+            .collect::<Vec<_>>();
+        let bloom_up_views = (0..bloom_levels)
+            .map(|level| mip_view(&bloom_up_texture, level))
+            .collect::<Vec<_>>();
+
+        let bloom_threshold_bind_group =
+            create_bind_group(&hdr_views[0], &hdr_views[0], &bloom_down_views[0]);
+        let bloom_downsample_bind_groups = bloom_down_views
+            .windows(2)
+            .map(|views| create_bind_group(&views[0], &views[0], &views[1]))
+            .collect();
+        let bloom_upsample_bind_groups = (0..bloom_levels.saturating_sub(1) as usize)
+            .map(|level| {
+                // the smallest level has nothing below it to add, so the level above it reads the downsampled version directly
+                let source = if level + 2 == bloom_levels as usize {
+                    &bloom_down_views[level + 1]
+                } else {
+                    &bloom_up_views[level + 1]
+                };
+                create_bind_group(source, &bloom_down_views[level], &bloom_up_views[level])
+            })
+            .collect();
+        let bloom_result = if bloom_levels > 1 {
+            &bloom_up_views[0]
+        } else {
+            &bloom_down_views[0]
+        };
+        let bloom_composite_bind_group =
+            create_bind_group(&hdr_views[0], bloom_result, &hdr_views[1]);
+
+        let bind_groups = [
+            create_bind_group(&hdr_views[0], &hdr_views[0], &hdr_views[1]),
+            create_bind_group(&hdr_views[1], &hdr_views[1], &hdr_views[0]),
+        ];
+
+        let main_texture_view = main_texture.create_view(&wgpu::TextureViewDescriptor::default());
+        let resolve_bind_groups = [&hdr_views[0], &hdr_views[1]].map(|hdr_view| {
+            device.create_bind_group(&wgpu::BindGroupDescriptor {
+                label: Some("Post Process Resolve Bind Group"),
+                layout: &pipelines.resolve_bind_group_layout,
+                entries: &[
+                    wgpu::BindGroupEntry {
+                        binding: 0,
+                        resource: wgpu::BindingResource::TextureView(hdr_view),
+                    },
+                    wgpu::BindGroupEntry {
+                        binding: 1,
+                        resource: wgpu::BindingResource::TextureView(hdr_view),
+                    },
+                    wgpu::BindGroupEntry {
+                        binding: 2,
+                        resource: wgpu::BindingResource::Sampler(&pipelines.sampler),
+                    },
+                    wgpu::BindGroupEntry {
+                        binding: 4,
+                        resource: wgpu::BindingResource::TextureView(&main_texture_view),
+                    },
+                ],
+            })
+        });
+
+        Self {
+            size: (width, height),
+            main_size: (main_texture.width(), main_texture.height()),
+
+            bloom_threshold_bind_group,
+            bloom_downsample_bind_groups,
+            bloom_upsample_bind_groups,
+            bloom_composite_bind_group,
+            bloom_sizes,
+
+            bind_groups,
+            resolve_bind_groups,
+        }
+    }
+
+    /// records every effect into `post_process_pass`, the render graph and a still that is saved share this
+    pub(super) fn record<'a>(
+        &'a self,
+        post_process_pass: &mut wgpu::ComputePass<'a>,
+        pipelines: &'a PostProcessPipelines,
+        settings_bind_group: &'a wgpu::BindGroup,
+        post_process: &PostProcess,
+        color_grading: Option<&ColorGrading>,
+        upscale_filter: UpscaleFilter,
+    ) {
+        let (width, height) = self.size;
+
+        post_process_pass.set_bind_group(1, settings_bind_group, &[]);
+
+        let mut dispatch = |pipeline, bind_group, (width, height): (u32, u32)| {
+            post_process_pass.set_pipeline(pipeline);
+            post_process_pass.set_bind_group(0, bind_group, &[]);
+            post_process_pass.dispatch_workgroups(width.div_ceil(16), height.div_ceil(16), 1);
+        };
+
+        let mut source = 0;
+        if post_process.bloom.is_some() {
+            dispatch(
+                &pipelines.bloom_threshold,
+                &self.bloom_threshold_bind_group,
+                self.bloom_sizes[0],
+            );
+            for (bind_group, &size) in self
+                .bloom_downsample_bind_groups
+                .iter()
+                .zip(&self.bloom_sizes[1..])
+            {
+                dispatch(&pipelines.bloom_downsample, bind_group, size);
+            }
+            for (bind_group, &size) in self
+                .bloom_upsample_bind_groups
+                .iter()
+                .zip(&self.bloom_sizes)
+                .rev()
+            {
+                dispatch(&pipelines.bloom_upsample, bind_group, size);
+            }
+            // bloom is always the first effect, so it always reads the texture the ray tracer wrote
+            dispatch(
+                &pipelines.bloom_composite,
+                &self.bloom_composite_bind_group,
+                (width, height),
+            );
+            source = 1;
+        }
+
+        for (enabled, pipeline) in [
+            (
+                post_process.chromatic_aberration.is_some(),
+                &pipelines.chromatic_aberration,
+            ),
+            (color_grading.is_some(), &pipelines.color_grading),
+            (
+                color_grading.is_some_and(|color_grading| color_grading.lut.is_some()),
+                &pipelines.lut,
+            ),
+            (post_process.vignette.is_some(), &pipelines.vignette),
+            (post_process.film_grain.is_some(), &pipelines.film_grain),
+        ] {
+            if enabled {
+                dispatch(pipeline, &self.bind_groups[source], (width, height));
+                source = 1 - source;
+            }
+        }
+
+        let resolve_pipeline = match upscale_filter {
+            UpscaleFilter::Bilinear => &pipelines.resolve,
+            UpscaleFilter::EdgeAware => &pipelines.resolve_edge_aware,
+        };
+        dispatch(
+            resolve_pipeline,
+            &self.resolve_bind_groups[source],
+            self.main_size,
+        );
+    }
+}
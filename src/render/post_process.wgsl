@@ -0,0 +1,392 @@
+struct PostProcess {
+    bloom_threshold: f32,
+    bloom_intensity: f32,
+    chromatic_aberration_intensity: f32,
+    vignette_intensity: f32,
+    vignette_radius: f32,
+    vignette_smoothness: f32,
+    film_grain_intensity: f32,
+    frame: u32,
+    exposure: f32,
+    contrast: f32,
+    saturation: f32,
+    tonemapping: u32,
+    white_balance: vec3<f32>,
+    lift: vec3<f32>,
+    gamma: vec3<f32>,
+    gain: vec3<f32>,
+    lut_domain_min: vec3<f32>,
+    lut_domain_max: vec3<f32>,
+}
+
+const TONEMAPPING_NONE: u32 = 0u;
+const TONEMAPPING_REINHARD: u32 = 1u;
+const TONEMAPPING_ACES: u32 = 2u;
+
+@group(0)
+@binding(0)
+var source_texture: texture_2d<f32>;
+
+@group(0)
+@binding(1)
+var secondary_texture: texture_2d<f32>;
+
+@group(0)
+@binding(2)
+var linear_sampler: sampler;
+
+@group(0)
+@binding(3)
+var output_texture: texture_storage_2d<rgba16float, write>;
+
+@group(0)
+@binding(4)
+var resolve_texture: texture_storage_2d<rgba8unorm, write>;
+
+@group(1)
+@binding(0)
+var<uniform> post_process: PostProcess;
+
+@group(1)
+@binding(1)
+var lut_texture: texture_3d<f32>;
+
+fn pixel_uv(coords: vec2<u32>, size: vec2<u32>) -> vec2<f32> {
+    return (vec2<f32>(coords) + 0.5) / vec2<f32>(size);
+}
+
+fn sample_source(uv: vec2<f32>) -> vec3<f32> {
+    return textureSampleLevel(source_texture, linear_sampler, uv, 0.0).rgb;
+}
+
+@compute
+@workgroup_size(16, 16)
+fn bloom_threshold(
+    @builtin(global_invocation_id) global_id: vec3<u32>,
+) {
+    let size = textureDimensions(output_texture);
+    let coords = global_id.xy;
+
+    if coords.x >= size.x || coords.y >= size.y {
+        return;
+    }
+
+    // the output is half the size of the source, so the bilinear sample averages 4 pixels
+    let color = sample_source(pixel_uv(coords, size));
+    let brightness = max(color.r, max(color.g, color.b));
+    let contribution = max(brightness - post_process.bloom_threshold, 0.0) / max(brightness, 0.0001);
+    textureStore(output_texture, coords, vec4<f32>(color * contribution, 1.0));
+}
+
+@compute
+@workgroup_size(16, 16)
+fn bloom_downsample(
+    @builtin(global_invocation_id) global_id: vec3<u32>,
+) {
+    let size = textureDimensions(output_texture);
+    let coords = global_id.xy;
+
+    if coords.x >= size.x || coords.y >= size.y {
+        return;
+    }
+
+    let uv = pixel_uv(coords, size);
+    let texel = 1.0 / vec2<f32>(textureDimensions(source_texture));
+    var color = vec3<f32>(0.0);
+    color += sample_source(uv + texel * vec2<f32>(-1.0, -1.0));
+    color += sample_source(uv + texel * vec2<f32>(1.0, -1.0));
+    color += sample_source(uv + texel * vec2<f32>(-1.0, 1.0));
+    color += sample_source(uv + texel * vec2<f32>(1.0, 1.0));
+    textureStore(output_texture, coords, vec4<f32>(color * 0.25, 1.0));
+}
+
+@compute
+@workgroup_size(16, 16)
+fn bloom_upsample(
+    @builtin(global_invocation_id) global_id: vec3<u32>,
+) {
+    let size = textureDimensions(output_texture);
+    let coords = global_id.xy;
+
+    if coords.x >= size.x || coords.y >= size.y {
+        return;
+    }
+
+    // 3x3 tent filter over the smaller level, added on top of the downsampled level of the same size
+    let uv = pixel_uv(coords, size);
+    let texel = 1.0 / vec2<f32>(textureDimensions(source_texture));
+    var color = sample_source(uv) * 4.0;
+    color += sample_source(uv + texel * vec2<f32>(-1.0, 0.0)) * 2.0;
+    color += sample_source(uv + texel * vec2<f32>(1.0, 0.0)) * 2.0;
+    color += sample_source(uv + texel * vec2<f32>(0.0, -1.0)) * 2.0;
+    color += sample_source(uv + texel * vec2<f32>(0.0, 1.0)) * 2.0;
+    color += sample_source(uv + texel * vec2<f32>(-1.0, -1.0));
+    color += sample_source(uv + texel * vec2<f32>(1.0, -1.0));
+    color += sample_source(uv + texel * vec2<f32>(-1.0, 1.0));
+    color += sample_source(uv + texel * vec2<f32>(1.0, 1.0));
+    color /= 16.0;
+
+    color += textureLoad(secondary_texture, coords, 0).rgb;
+    textureStore(output_texture, coords, vec4<f32>(color, 1.0));
+}
+
+@compute
+@workgroup_size(16, 16)
+fn bloom_composite(
+    @builtin(global_invocation_id) global_id: vec3<u32>,
+) {
+    let size = textureDimensions(output_texture);
+    let coords = global_id.xy;
+
+    if coords.x >= size.x || coords.y >= size.y {
+        return;
+    }
+
+    let bloom = textureSampleLevel(secondary_texture, linear_sampler, pixel_uv(coords, size), 0.0).rgb;
+    let color = textureLoad(source_texture, coords, 0).rgb + bloom * post_process.bloom_intensity;
+    textureStore(output_texture, coords, vec4<f32>(color, 1.0));
+}
+
+@compute
+@workgroup_size(16, 16)
+fn chromatic_aberration(
+    @builtin(global_invocation_id) global_id: vec3<u32>,
+) {
+    let size = textureDimensions(output_texture);
+    let coords = global_id.xy;
+
+    if coords.x >= size.x || coords.y >= size.y {
+        return;
+    }
+
+    let uv = pixel_uv(coords, size);
+    let offset = (uv - 0.5) * 2.0 * post_process.chromatic_aberration_intensity;
+    let color = vec3<f32>(
+        sample_source(uv - offset).r,
+        sample_source(uv).g,
+        sample_source(uv + offset).b,
+    );
+    textureStore(output_texture, coords, vec4<f32>(color, 1.0));
+}
+
+@compute
+@workgroup_size(16, 16)
+fn vignette(
+    @builtin(global_invocation_id) global_id: vec3<u32>,
+) {
+    let size = textureDimensions(output_texture);
+    let coords = global_id.xy;
+
+    if coords.x >= size.x || coords.y >= size.y {
+        return;
+    }
+
+    let distance = length(pixel_uv(coords, size) - 0.5) * 2.0;
+    let darkening = smoothstep(
+        post_process.vignette_radius,
+        post_process.vignette_radius + post_process.vignette_smoothness,
+        distance,
+    );
+    let color = textureLoad(source_texture, coords, 0).rgb * (1.0 - darkening * post_process.vignette_intensity);
+    textureStore(output_texture, coords, vec4<f32>(color, 1.0));
+}
+
+fn luminance(color: vec3<f32>) -> f32 {
+    return dot(color, vec3<f32>(0.2126, 0.7152, 0.0722));
+}
+
+fn linear_to_lms(color: vec3<f32>) -> vec3<f32> {
+    return vec3<f32>(
+        dot(color, vec3<f32>(3.90405e-1, 5.49941e-1, 8.92632e-3)),
+        dot(color, vec3<f32>(7.08416e-2, 9.63172e-1, 1.35775e-3)),
+        dot(color, vec3<f32>(2.31082e-2, 1.28021e-1, 9.36245e-1)),
+    );
+}
+
+fn lms_to_linear(color: vec3<f32>) -> vec3<f32> {
+    return vec3<f32>(
+        dot(color, vec3<f32>(2.85847e+0, -1.62879e+0, -2.48910e-2)),
+        dot(color, vec3<f32>(-2.10182e-1, 1.15820e+0, 3.24281e-4)),
+        dot(color, vec3<f32>(-4.18120e-2, -1.18169e-1, 1.06867e+0)),
+    );
+}
+
+fn tonemap_aces(color: vec3<f32>) -> vec3<f32> {
+    let a = 2.51;
+    let b = 0.03;
+    let c = 2.43;
+    let d = 0.59;
+    let e = 0.14;
+    return clamp((color * (a * color + b)) / (color * (c * color + d) + e), vec3<f32>(0.0), vec3<f32>(1.0));
+}
+
+@compute
+@workgroup_size(16, 16)
+fn color_grading(
+    @builtin(global_invocation_id) global_id: vec3<u32>,
+) {
+    let size = textureDimensions(output_texture);
+    let coords = global_id.xy;
+
+    if coords.x >= size.x || coords.y >= size.y {
+        return;
+    }
+
+    var color = textureLoad(source_texture, coords, 0).rgb;
+    color *= exp2(post_process.exposure);
+    color = max(lms_to_linear(linear_to_lms(color) * post_process.white_balance), vec3<f32>(0.0));
+
+    // contrast pivots around middle grey so it doesn't change the overall brightness
+    color = pow(color / 0.18, vec3<f32>(post_process.contrast)) * 0.18;
+    color = max(mix(vec3<f32>(luminance(color)), color, post_process.saturation), vec3<f32>(0.0));
+
+    color = post_process.gain * (color + post_process.lift * (1.0 - color));
+    color = pow(max(color, vec3<f32>(0.0)), 1.0 / post_process.gamma);
+
+    switch post_process.tonemapping {
+        case TONEMAPPING_REINHARD: {
+            color = color / (1.0 + color);
+        }
+        case TONEMAPPING_ACES: {
+            color = tonemap_aces(color);
+        }
+        default: {}
+    }
+
+    textureStore(output_texture, coords, vec4<f32>(color, 1.0));
+}
+
+@compute
+@workgroup_size(16, 16)
+fn lut(
+    @builtin(global_invocation_id) global_id: vec3<u32>,
+) {
+    let size = textureDimensions(output_texture);
+    let coords = global_id.xy;
+
+    if coords.x >= size.x || coords.y >= size.y {
+        return;
+    }
+
+    let color = textureLoad(source_texture, coords, 0).rgb;
+    let normalized = clamp(
+        (color - post_process.lut_domain_min) / (post_process.lut_domain_max - post_process.lut_domain_min),
+        vec3<f32>(0.0),
+        vec3<f32>(1.0),
+    );
+
+    // rgba32float can't be filtered, so the trilinear interpolation is done by hand
+    let lut_size = textureDimensions(lut_texture);
+    let position = normalized * vec3<f32>(lut_size - 1u);
+    let low = vec3<u32>(floor(position));
+    let high = min(low + 1u, lut_size - 1u);
+    let t = position - floor(position);
+
+    let c000 = textureLoad(lut_texture, vec3<u32>(low.x, low.y, low.z), 0).rgb;
+    let c100 = textureLoad(lut_texture, vec3<u32>(high.x, low.y, low.z), 0).rgb;
+    let c010 = textureLoad(lut_texture, vec3<u32>(low.x, high.y, low.z), 0).rgb;
+    let c110 = textureLoad(lut_texture, vec3<u32>(high.x, high.y, low.z), 0).rgb;
+    let c001 = textureLoad(lut_texture, vec3<u32>(low.x, low.y, high.z), 0).rgb;
+    let c101 = textureLoad(lut_texture, vec3<u32>(high.x, low.y, high.z), 0).rgb;
+    let c011 = textureLoad(lut_texture, vec3<u32>(low.x, high.y, high.z), 0).rgb;
+    let c111 = textureLoad(lut_texture, vec3<u32>(high.x, high.y, high.z), 0).rgb;
+
+    let c00 = mix(c000, c100, t.x);
+    let c10 = mix(c010, c110, t.x);
+    let c01 = mix(c001, c101, t.x);
+    let c11 = mix(c011, c111, t.x);
+    let graded = mix(mix(c00, c10, t.y), mix(c01, c11, t.y), t.z);
+
+    textureStore(output_texture, coords, vec4<f32>(graded, 1.0));
+}
+
+fn hash(value: u32) -> u32 {
+    let state = value * 747796405u + 2891336453u;
+    let word = ((state >> ((state >> 28u) + 4u)) ^ state) * 277803737u;
+    return (word >> 22u) ^ word;
+}
+
+@compute
+@workgroup_size(16, 16)
+fn film_grain(
+    @builtin(global_invocation_id) global_id: vec3<u32>,
+) {
+    let size = textureDimensions(output_texture);
+    let coords = global_id.xy;
+
+    if coords.x >= size.x || coords.y >= size.y {
+        return;
+    }
+
+    let random = f32(hash(coords.x ^ hash(coords.y ^ hash(post_process.frame)))) / f32(0xFFFFFFFFu);
+    let noise = (random * 2.0 - 1.0) * post_process.film_grain_intensity;
+    let color = max(textureLoad(source_texture, coords, 0).rgb + noise, vec3<f32>(0.0));
+    textureStore(output_texture, coords, vec4<f32>(color, 1.0));
+}
+
+fn store_resolved(coords: vec2<u32>, color: vec3<f32>) {
+    textureStore(resolve_texture, coords, vec4<f32>(clamp(color, vec3<f32>(0.0), vec3<f32>(1.0)), 1.0));
+}
+
+// the source is smaller or larger than the main texture when the render scale is not 1
+@compute
+@workgroup_size(16, 16)
+fn resolve(
+    @builtin(global_invocation_id) global_id: vec3<u32>,
+) {
+    let size = textureDimensions(resolve_texture);
+    let coords = global_id.xy;
+
+    if coords.x >= size.x || coords.y >= size.y {
+        return;
+    }
+
+    if all(textureDimensions(source_texture) == size) {
+        store_resolved(coords, textureLoad(source_texture, coords, 0).rgb);
+    } else {
+        store_resolved(coords, sample_source(pixel_uv(coords, size)));
+    }
+}
+
+// how strongly a difference in luminance to the closest source pixel lowers the weight of a pixel
+const EDGE_SHARPNESS: f32 = 8.0;
+
+@compute
+@workgroup_size(16, 16)
+fn resolve_edge_aware(
+    @builtin(global_invocation_id) global_id: vec3<u32>,
+) {
+    let size = textureDimensions(resolve_texture);
+    let coords = global_id.xy;
+
+    if coords.x >= size.x || coords.y >= size.y {
+        return;
+    }
+
+    let source_size = textureDimensions(source_texture);
+    if all(source_size == size) {
+        store_resolved(coords, textureLoad(source_texture, coords, 0).rgb);
+        return;
+    }
+
+    // bilinear weights of the 4 closest source pixels, lowered for the ones that differ from the closest pixel,
+    // so pixels on the other side of an edge don't blur into it
+    let position = pixel_uv(coords, size) * vec2<f32>(source_size) - 0.5;
+    let base = vec2<i32>(floor(position));
+    let t = position - floor(position);
+    let max_coords = vec2<i32>(source_size) - 1;
+    let closest = textureLoad(source_texture, clamp(vec2<i32>(round(position)), vec2<i32>(0), max_coords), 0).rgb;
+
+    var color = vec3<f32>(0.0);
+    var total_weight = 0.0;
+    for (var y = 0; y < 2; y += 1) {
+        for (var x = 0; x < 2; x += 1) {
+            let texel = textureLoad(source_texture, clamp(base + vec2<i32>(x, y), vec2<i32>(0), max_coords), 0).rgb;
+            let bilinear = select(1.0 - t.x, t.x, x == 1) * select(1.0 - t.y, t.y, y == 1);
+            let weight = bilinear / (1.0 + EDGE_SHARPNESS * abs(luminance(texel) - luminance(closest)));
+            color += texel * weight;
+            total_weight += weight;
+        }
+    }
+    store_resolved(coords, color / max(total_weight, 0.0001));
+}
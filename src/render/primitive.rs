@@ -0,0 +1,114 @@
+use crate::{
+    math::Motor,
+    render::{
+        render_state::{PrimitiveState, RenderRecovery},
+        MaterialHandle, RenderSchedule, RenderSet, ShaderStruct, Sphere,
+    },
+    transform::{GlobalTransform, PreviousGlobalTransform},
+};
+use bevy::{
+    app::App,
+    ecs::{component::Component, schedule::IntoSystemConfigs, world::Mut},
+};
+use encase::{internal::WriteInto, ShaderSize, ShaderType};
+
+/// a component that the ray tracer can intersect rays with
+///
+/// every entity with this component, a [`GlobalTransform`] and a [`MaterialHandle`] is uploaded to its own storage buffer,
+/// and the ray tracer calls the intersection function of the type for each of them
+///
+/// for motion blur, rays have a `time` from 0 at the [`PreviousGlobalTransform`] to 1 at the [`GlobalTransform`],
+/// primitives that move should blend between the two with `interpolate_motor`
+pub trait Primitive: Component {
+    /// the data of a single primitive on the gpu, deriving [`ShaderType`],
+    /// it must have the same layout as the wgsl struct [`Primitive::SHADER_TYPE`], which is checked when the ray tracing pipeline is created
+    type GpuData: ShaderType + ShaderSize + ShaderStruct + WriteInto + Send + Sync + 'static;
+
+    /// the name of the wgsl struct declared in [`Primitive::SHADER`]
+    const SHADER_TYPE: &'static str;
+    /// the name of the wgsl function declared in [`Primitive::SHADER`] with the signature `fn(ray: Ray, primitive: SHADER_TYPE) -> Hit`
+    const SHADER_INTERSECT: &'static str;
+    /// wgsl that declares the struct and the intersection function,
+    /// it can use `Ray`, `Hit`, `camera` and the motor functions of the ray tracing shader,
+    /// and the directives of [`compose_shader`](super::compose_shader)
+    ///
+    /// the intersection function has to set `Hit::material` to the index of the material, which should be part of [`Primitive::GpuData`],
+    /// a hit with a `Hit::medium_density` above 0 enters a volume that the ray leaves again at `Hit::exit_distance`
+    const SHADER: &'static str;
+    /// the name of the file in the directory of [`ShaderHotReload`](super::ShaderHotReload) that [`Primitive::SHADER`] was included from,
+    /// so it is reloaded together with the ray tracing shader
+    const SHADER_FILE: Option<&'static str> = None;
+    /// calls [`Primitive::SHADER_INTERSECT`] with the index of the primitive instead of a copy of it, so the signature is `fn(ray: Ray, index: u32) -> Hit`,
+    /// for large primitives that only read some of their fields from `primitives_<SHADER_TYPE>.data[index].value`, the storage buffer of the type
+    const SHADER_INTERSECT_BY_INDEX: bool = false;
+
+    fn gpu_data(
+        &self,
+        transform: &GlobalTransform,
+        previous_transform: &PreviousGlobalTransform,
+        material: MaterialHandle,
+    ) -> Self::GpuData;
+}
+
+pub trait PrimitiveAppExt {
+    /// makes the ray tracer draw every entity with the primitive component `T`, registering the same type twice does nothing
+    fn register_primitive<T: Primitive>(&mut self) -> &mut Self;
+}
+
+impl PrimitiveAppExt for App {
+    fn register_primitive<T: Primitive>(&mut self) -> &mut Self {
+        let registered = self
+            .world
+            .resource_mut::<RenderRecovery>()
+            .register_primitive::<T>();
+        if registered {
+            // without a renderer the type is registered when the renderer is created
+            if self.world.contains_resource::<PrimitiveState>() {
+                self.world
+                    .resource_scope(|world, mut primitive_state: Mut<PrimitiveState>| {
+                        primitive_state.register::<T>(world)
+                    });
+            }
+            self.add_systems(
+                RenderSchedule,
+                super::render_state::update_primitives::<T>.in_set(RenderSet::Update),
+            );
+        }
+        self
+    }
+}
+
+#[derive(ShaderType)]
+pub struct GpuSphere {
+    transform: Motor,
+    previous_transform: Motor,
+    radius: f32,
+    medium_density: f32,
+    medium_anisotropy: f32,
+    material: u32,
+}
+
+impl Primitive for Sphere {
+    type GpuData = GpuSphere;
+
+    const SHADER_TYPE: &'static str = "Sphere";
+    const SHADER_INTERSECT: &'static str = "intersect_sphere";
+    const SHADER: &'static str = include_str!("./sphere.wgsl");
+    const SHADER_FILE: Option<&'static str> = Some("sphere.wgsl");
+
+    fn gpu_data(
+        &self,
+        transform: &GlobalTransform,
+        previous_transform: &PreviousGlobalTransform,
+        material: MaterialHandle,
+    ) -> Self::GpuData {
+        GpuSphere {
+            transform: transform.transform().motor,
+            previous_transform: previous_transform.transform().motor,
+            radius: self.radius,
+            medium_density: self.medium.map_or(0.0, |medium| medium.density),
+            medium_anisotropy: self.medium.map_or(0.0, |medium| medium.anisotropy),
+            material: material.index(),
+        }
+    }
+}
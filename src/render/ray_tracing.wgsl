@@ -1,37 +1,96 @@
+#import math
+#import camera
+
 @group(0)
 @binding(0)
-var output_texture: texture_storage_2d<rgba8unorm, write>;
+var output_texture: texture_storage_2d<rgba16float, write>;
+
+@group(0)
+@binding(1)
+var depth_texture: texture_storage_2d<r32float, write>;
 
-struct Camera {
-    transform: Motor,
-    v_fov: f32,
-    min_distance: f32,
-    max_distance: f32,
-    sun_direction: vec3<f32>,
+// the renderer only counts rays together with COUNT_INTERSECTION_TESTS
+#ifdef COUNT_RAYS
+// has to match `RayCounters`, every count is a 64 bit number of a low and a high word, because 32 bits overflow within a frame
+const PRIMARY_RAY_COUNTER: u32 = 0u;
+const SHADOW_RAY_COUNTER: u32 = 1u;
+const BOUNCE_RAY_COUNTER: u32 = 2u;
+const INTERSECTION_TEST_COUNTER: u32 = 3u;
+
+@group(0)
+@binding(2)
+var<storage, read_write> ray_counters: array<atomic<u32>, 8>;
+
+fn add_to_ray_counter(counter: u32, count: u32) {
+    let low = atomicAdd(&ray_counters[counter * 2u], count);
+    // the low word wrapped around
+    if low + count < low {
+        atomicAdd(&ray_counters[counter * 2u + 1u], 1u);
+    }
 }
 
+var<private> shadow_rays: u32;
+var<private> bounce_rays: u32;
+#endif
+
+// has to match `GpuRenderTile`, the part of the image that is rendered into the output texture,
+// which is the whole image unless the image is rendered in tiles
+struct RenderTile {
+    offset_x: u32,
+    offset_y: u32,
+    image_width: u32,
+    image_height: u32,
+    // rays per pixel of this dispatch
+    samples: u32,
+    // the samples of the dispatches before, so every dispatch gets other random numbers
+    first_sample: u32,
+    // rays per pixel of all dispatches, with random offsets within the pixel when there is more than one
+    total_samples: u32,
+}
+
+@group(0)
+@binding(3)
+var<uniform> tile: RenderTile;
+
+// the depth written for pixels where the primary ray didn't hit anything
+const FAR_DEPTH: f32 = 3.0e38;
+
+const DEBUG_VIEW_SHADED: u32 = 0u;
+const DEBUG_VIEW_NORMALS: u32 = 1u;
+const DEBUG_VIEW_DEPTH: u32 = 2u;
+const DEBUG_VIEW_ALBEDO: u32 = 3u;
+const DEBUG_VIEW_HIT_DISTANCE: u32 = 4u;
+const DEBUG_VIEW_SHADOW_MASK: u32 = 5u;
+const DEBUG_VIEW_INTERSECTION_TESTS: u32 = 6u;
+
+const MEDIA_RENDERING_ANALYTIC: u32 = 0u;
+const MEDIA_RENDERING_PATH_TRACED: u32 = 1u;
+
 @group(1)
 @binding(0)
 var<uniform> camera: Camera;
 
-struct Sphere {
-    transform: Motor,
+struct Material {
     color: vec3<f32>,
-    radius: f32,
+    emission: vec3<f32>,
 }
 
-struct Spheres {
+struct Materials {
     length: u32,
-    data: array<Sphere>,
+    data: array<Material>,
 }
 
 @group(2)
 @binding(0)
-var<storage, read> spheres: Spheres;
+var<storage, read> materials: Materials;
+
+// the other bindings in group 2, `intersect_primitives` and `primitive_count` are generated for every registered primitive type
 
 struct Ray {
     origin: vec3<f32>,
     direction: vec3<f32>,
+    // when the ray was sent during the frame, from 0 at the previous transforms of the primitives to 1 at their current ones
+    time: f32,
 }
 
 struct Hit {
@@ -39,113 +98,320 @@ struct Hit {
     distance: f32,
     position: vec3<f32>,
     normal: vec3<f32>,
-    color: vec3<f32>,
+    material: u32,
+    // above 0 when the ray enters a medium at `distance` instead of hitting a surface
+    medium_density: f32,
+    medium_anisotropy: f32,
+    // where the ray leaves the medium again
+    exit_distance: f32,
 }
 
-fn intersect_sphere(ray: Ray, sphere: Sphere) -> Hit {
-    var hit: Hit;
-    hit.hit = false;
-    hit.color = sphere.color;
+#ifdef COUNT_INTERSECTION_TESTS
+var<private> intersection_tests: u32;
+#endif
 
-    let sphere_position = point_to_vec3(transform_point(vec3_to_point(vec3<f32>(0.0)), sphere.transform));
-    let oc = ray.origin - sphere_position;
-    let a = dot(ray.direction, ray.direction);
-    let half_b = dot(oc, ray.direction);
-    let c = dot(oc, oc) - sphere.radius * sphere.radius;
-    let discriminant = half_b * half_b - a * c;
+fn intersect_ray(ray: Ray) -> Hit {
+    return intersect_primitives(ray);
+}
 
-    if discriminant < 0.0 {
-        return hit;
-    }
+var<private> random_state: u32;
 
-    let sqrt_discriminant = sqrt(discriminant);
-    let t0 = (-half_b - sqrt_discriminant) / a;
-    let t1 = (-half_b + sqrt_discriminant) / a;
+fn hash(value: u32) -> u32 {
+    let state = value * 747796405u + 2891336453u;
+    let word = ((state >> ((state >> 28u) + 4u)) ^ state) * 277803737u;
+    return (word >> 22u) ^ word;
+}
 
-    if t0 > camera.min_distance {
-        hit.distance = t0;
-    } else {
-        hit.distance = t1;
-    }
+// a random number in [0, 1), the sequence is the same every frame for the same pixel
+fn random() -> f32 {
+    random_state = hash(random_state);
+    return f32(random_state) / 4294967296.0;
+}
 
-    if hit.distance < camera.min_distance || camera.max_distance < hit.distance {
-        return hit;
+fn skybox(ray: Ray) -> vec3<f32> {
+    let t = ray.direction.y * 0.5 + 0.5;
+    let up = vec3<f32>(0.1, 0.2, 0.8);
+    let down = vec3<f32>(0.7, 0.7, 0.8);
+    return up * t + down * (1.0 - t);
+}
+
+fn heatmap(t: f32) -> vec3<f32> {
+    let x = clamp(t, 0.0, 1.0);
+    return clamp(vec3<f32>(
+        1.5 - abs(4.0 * x - 3.0),
+        1.5 - abs(4.0 * x - 2.0),
+        1.5 - abs(4.0 * x - 1.0),
+    ), vec3<f32>(0.0), vec3<f32>(1.0));
+}
+
+// the light of the sun and the light coming from the sky in every direction, that the fog and media scatter
+const SUN_IRRADIANCE: vec3<f32> = vec3<f32>(10.0, 9.5, 9.0);
+const AMBIENT_LIGHT: vec3<f32> = vec3<f32>(0.4, 0.45, 0.8);
+// how many media a ray passes through at most, after that it is treated as blocked
+const MAX_MEDIUM_CROSSINGS: u32 = 8u;
+// how many steps delta and ratio tracking take at most through the fog along one ray
+const MAX_TRACKING_STEPS: u32 = 64u;
+
+const PI: f32 = 3.14159265;
+
+fn fog_density(position: vec3<f32>) -> f32 {
+    return camera.fog_density * exp(-camera.fog_height_falloff * (position.y - camera.fog_height));
+}
+
+// the fog density integrated along the ray from its origin to `distance`
+fn fog_optical_depth(ray: Ray, distance: f32) -> f32 {
+    let falloff = camera.fog_height_falloff * ray.direction.y;
+    if abs(falloff) < 0.0001 {
+        return fog_density(ray.origin) * distance;
     }
+    return fog_density(ray.origin) * (1.0 - exp(-falloff * distance)) / falloff;
+}
 
-    hit.position = ray.origin + ray.direction * hit.distance;
-    hit.normal = normalize(hit.position - sphere_position);
-    if dot(hit.normal, ray.origin - hit.position) < 0.0 {
-        hit.normal *= -1.0;
+// the inverse of `fog_optical_depth`, the distance along the ray at which the optical depth is reached
+fn fog_distance(ray: Ray, optical_depth: f32) -> f32 {
+    let falloff = camera.fog_height_falloff * ray.direction.y;
+    if abs(falloff) < 0.0001 {
+        return optical_depth / fog_density(ray.origin);
     }
+    return -log(max(1.0 - optical_depth * falloff / fog_density(ray.origin), 0.0)) / falloff;
+}
 
-    hit.hit = true;
-    return hit;
+// the density only changes exponentially with the height, so it is largest at one of the ends of the ray
+fn fog_majorant(ray: Ray, distance: f32) -> f32 {
+    return max(fog_density(ray.origin), fog_density(ray.origin + ray.direction * distance));
 }
 
-fn intersect_ray(ray: Ray) -> Hit {
-    var closest_hit: Hit;
-    closest_hit.hit = false;
+// estimates the transmittance through the fog from the ray origin to `distance` with ratio tracking
+fn fog_ratio_tracking(ray: Ray, distance: f32) -> f32 {
+    let majorant = fog_majorant(ray, distance);
+    var transmittance = 1.0;
+    var t = 0.0;
+    for (var step = 0u; step < MAX_TRACKING_STEPS; step += 1u) {
+        t -= log(1.0 - random()) / majorant;
+        if t >= distance {
+            break;
+        }
+        transmittance *= 1.0 - fog_density(ray.origin + ray.direction * t) / majorant;
+    }
+    return transmittance;
+}
 
-    var sphere_index = 0u;
-    while sphere_index < spheres.length {
-        let hit = intersect_sphere(ray, spheres.data[sphere_index]);
-        if hit.hit && (!closest_hit.hit || hit.distance < closest_hit.distance) {
-            closest_hit = hit;
+// samples where light along the ray collides with the fog with delta tracking, `distance` when it gets there without
+fn fog_delta_tracking(ray: Ray, distance: f32) -> f32 {
+    let majorant = fog_majorant(ray, distance);
+    var t = 0.0;
+    for (var step = 0u; step < MAX_TRACKING_STEPS; step += 1u) {
+        t -= log(1.0 - random()) / majorant;
+        if t >= distance {
+            break;
+        }
+        if random() * majorant < fog_density(ray.origin + ray.direction * t) {
+            return t;
         }
-        sphere_index += 1u;
     }
+    return distance;
+}
 
-    return closest_hit;
+fn fog_transmittance(ray: Ray, distance: f32) -> f32 {
+    if camera.fog_density <= 0.0 {
+        return 1.0;
+    }
+    if camera.media_rendering == MEDIA_RENDERING_PATH_TRACED {
+        return fog_ratio_tracking(ray, distance);
+    }
+    return exp(-fog_optical_depth(ray, distance));
 }
 
-fn skybox(ray: Ray) -> vec3<f32> {
-    let t = ray.direction.y * 0.5 + 0.5;
-    let up = vec3<f32>(0.1, 0.2, 0.8);
-    let down = vec3<f32>(0.7, 0.7, 0.8);
-    return up * t + down * (1.0 - t);
+// how much of the light traveling along `ray` is scattered by the angle with `cos_theta`
+fn henyey_greenstein(cos_theta: f32, anisotropy: f32) -> f32 {
+    let g2 = anisotropy * anisotropy;
+    let denominator = 1.0 + g2 - 2.0 * anisotropy * cos_theta;
+    return (1.0 - g2) / (4.0 * PI * denominator * sqrt(denominator));
 }
 
-fn trace(ray: Ray) -> vec3<f32> {
-    let hit = intersect_ray(ray);
-    if hit.hit {
-        var new_ray: Ray;
-        new_ray.origin = hit.position;
-        new_ray.direction = camera.sun_direction;
+// a random direction that light traveling in `direction` is scattered to, distributed like `henyey_greenstein`
+fn sample_henyey_greenstein(direction: vec3<f32>, anisotropy: f32) -> vec3<f32> {
+    var cos_theta = 1.0 - 2.0 * random();
+    if abs(anisotropy) > 0.001 {
+        let g2 = anisotropy * anisotropy;
+        let s = (1.0 - g2) / (1.0 - anisotropy + 2.0 * anisotropy * random());
+        cos_theta = (1.0 + g2 - s * s) / (2.0 * anisotropy);
+    }
+    let sin_theta = sqrt(max(1.0 - cos_theta * cos_theta, 0.0));
+    let phi = 2.0 * PI * random();
 
-        let new_hit = intersect_ray(new_ray);
-        var color = hit.color;
+    let helper = select(vec3<f32>(1.0, 0.0, 0.0), vec3<f32>(0.0, 1.0, 0.0), abs(direction.x) > 0.9);
+    let tangent = normalize(cross(direction, helper));
+    let bitangent = cross(direction, tangent);
+    return (tangent * cos(phi) + bitangent * sin(phi)) * sin_theta + direction * cos_theta;
+}
 
-        let light = dot(hit.normal, camera.sun_direction) * 0.5 + 0.5;
-        color *= max(f32(!new_hit.hit) * light, 0.5);
+// how much sunlight reaches `position` through the fog and media, 0 when a surface is in the way
+fn sun_transmittance(position: vec3<f32>, time: f32) -> f32 {
+#ifdef COUNT_RAYS
+    shadow_rays += 1u;
+#endif
+    var ray: Ray;
+    ray.origin = position;
+    ray.direction = camera.sun_direction;
+    ray.time = time;
+
+    var transmittance = 1.0;
+    for (var crossing = 0u; crossing <= MAX_MEDIUM_CROSSINGS; crossing += 1u) {
+        let hit = intersect_ray(ray);
+        transmittance *= fog_transmittance(ray, select(camera.max_distance, hit.distance, hit.hit));
+        if !hit.hit {
+            return transmittance;
+        }
+        if hit.medium_density <= 0.0 {
+            return 0.0;
+        }
+        // a homogeneous medium has a closed form transmittance, ratio tracking would only estimate it
+        transmittance *= exp(-hit.medium_density * (hit.exit_distance - hit.distance));
+        ray.origin += ray.direction * hit.exit_distance;
+    }
+    return 0.0;
+}
+
+// the light scattered at `position` toward the origin of the ray, without the albedo
+fn in_scattered_light(position: vec3<f32>, ray: Ray, anisotropy: f32) -> vec3<f32> {
+    let phase = henyey_greenstein(dot(ray.direction, camera.sun_direction), anisotropy);
+    return AMBIENT_LIGHT + SUN_IRRADIANCE * phase * sun_transmittance(position, ray.time);
+}
 
-        return color;
-    } else {
-        return skybox(ray);
+// the debug views that show properties of the primary hit
+fn debug_color(ray: Ray, hit: Hit) -> vec3<f32> {
+    if !hit.hit {
+        if camera.debug_view == DEBUG_VIEW_ALBEDO {
+            return skybox(ray);
+        }
+        return vec3<f32>(0.0);
     }
 
-    // var ray = ray_;
+    switch camera.debug_view {
+        case DEBUG_VIEW_NORMALS: {
+            return hit.normal * 0.5 + 0.5;
+        }
+        case DEBUG_VIEW_DEPTH: {
+            let depth = (hit.distance - camera.min_distance) / (camera.max_distance - camera.min_distance);
+            return vec3<f32>(1.0 - depth);
+        }
+        case DEBUG_VIEW_ALBEDO: {
+            return materials.data[hit.material].color;
+        }
+        case DEBUG_VIEW_HIT_DISTANCE: {
+            return heatmap(log2(1.0 + hit.distance) / log2(1.0 + camera.max_distance));
+        }
+        default: {
+            return vec3<f32>(sun_transmittance(hit.position, ray.time));
+        }
+    }
+}
+
+fn shade_surface(ray: Ray, hit: Hit) -> vec3<f32> {
+    let material = materials.data[hit.material];
+    let light = dot(hit.normal, camera.sun_direction) * 0.5 + 0.5;
+    return material.color * max(sun_transmittance(hit.position, ray.time) * light, 0.5) + material.emission;
+}
 
-    // var color = vec3<f32>(1.0, 1.0, 1.0);
-    // var incoming_light = vec3<f32>(0.0, 0.0, 0.0);
+fn trace(primary_ray: Ray, primary_hit: Hit) -> vec3<f32> {
+    if camera.debug_view != DEBUG_VIEW_SHADED && camera.debug_view != DEBUG_VIEW_INTERSECTION_TESTS {
+        return debug_color(primary_ray, primary_hit);
+    }
 
-    // var bounce_index = 0u;
-    // while bounce_index < camera.max_bounces {
-    //     let hit = intersect_ray(ray);
-    //     if hit.hit {
-    //         ray.origin = hit.position;
-    //         ray.direction = reflect(ray.direction, hit.normal);
+    let path_traced = camera.media_rendering == MEDIA_RENDERING_PATH_TRACED;
+    var ray = primary_ray;
+    var hit = primary_hit;
+    // the light that reached the camera so far, and how much of the light from further along the ray reaches it
+    var radiance = vec3<f32>(0.0);
+    var throughput = vec3<f32>(1.0);
+    var bounces = 0u;
+    var crossings = 0u;
+    loop {
+        var scattered = false;
+        var scatter_position = vec3<f32>(0.0);
+        var anisotropy = 0.0;
+
+        // the fog in front of the hit
+        let distance = select(camera.max_distance, hit.distance, hit.hit);
+        if camera.fog_density > 0.0 {
+            if path_traced {
+                let scatter_distance = fog_delta_tracking(ray, distance);
+                if scatter_distance < distance {
+                    scattered = true;
+                    scatter_position = ray.origin + ray.direction * scatter_distance;
+                    anisotropy = camera.fog_anisotropy;
+                    throughput *= camera.fog_albedo;
+                }
+            } else {
+                let optical_depth = fog_optical_depth(ray, distance);
+                let transmittance = exp(-optical_depth);
+                // the light is scattered at a single point, which is picked where the fog scatters more of it,
+                // so the shadows in the fog average out to god rays
+                let scatter_optical_depth = -log(1.0 - random() * (1.0 - transmittance));
+                let position = ray.origin + ray.direction * min(fog_distance(ray, scatter_optical_depth), distance);
+                let light = in_scattered_light(position, ray, camera.fog_anisotropy);
+                radiance += throughput * (1.0 - transmittance) * camera.fog_albedo * light;
+                throughput *= transmittance;
+            }
+        }
+
+        if !scattered && hit.hit && hit.medium_density > 0.0 {
+            let material = materials.data[hit.material];
+            let length = hit.exit_distance - hit.distance;
+            if path_traced {
+                let scatter_distance = -log(1.0 - random()) / hit.medium_density;
+                if scatter_distance < length {
+                    scattered = true;
+                    scatter_position = hit.position + ray.direction * scatter_distance;
+                    anisotropy = hit.medium_anisotropy;
+                    radiance += throughput * material.emission;
+                    throughput *= material.color;
+                }
+            } else {
+                let transmittance = exp(-hit.medium_density * length);
+                let scatter_distance = -log(1.0 - random() * (1.0 - transmittance)) / hit.medium_density;
+                let light = in_scattered_light(hit.position + ray.direction * scatter_distance, ray, hit.medium_anisotropy);
+                radiance += throughput * (1.0 - transmittance) * (material.emission + material.color * light);
+                throughput *= transmittance;
+            }
+
+            if !scattered {
+                crossings += 1u;
+                if crossings > MAX_MEDIUM_CROSSINGS {
+                    break;
+                }
+                ray.origin += ray.direction * hit.exit_distance;
+                hit = intersect_ray(ray);
+                continue;
+            }
+        }
 
-    //         let emitted_light = vec3<f32>(0.0);
-    //         incoming_light += emitted_light * color;
-    //         color *= hit.color;
-    //     } else {
-    //         incoming_light += skybox(ray) * color;
-    //         break;
-    //     }
-    //     bounce_index += 1u;
-    // }
+        if scattered {
+            // the sky is hit by the bounces, so only the sun is sampled
+            let phase = henyey_greenstein(dot(ray.direction, camera.sun_direction), anisotropy);
+            radiance += throughput * SUN_IRRADIANCE * phase * sun_transmittance(scatter_position, ray.time);
+            if bounces >= camera.max_bounces {
+                break;
+            }
+            bounces += 1u;
+#ifdef COUNT_RAYS
+            bounce_rays += 1u;
+#endif
+            ray.origin = scatter_position;
+            ray.direction = sample_henyey_greenstein(ray.direction, anisotropy);
+            hit = intersect_ray(ray);
+            continue;
+        }
 
-    // return incoming_light;
+        if hit.hit {
+            radiance += throughput * shade_surface(ray, hit);
+        } else {
+            radiance += throughput * skybox(ray);
+        }
+        break;
+    }
+    return radiance;
 }
 
 @compute
@@ -160,92 +426,56 @@ fn ray_trace(
         return;
     }
 
-    var ray: Ray;
-    ray.origin = point_to_vec3(transform_point(vec3_to_point(vec3<f32>(0.0)), camera.transform));
+    // tiles at the edge of the image reach past it
+    let image_size = vec2<u32>(tile.image_width, tile.image_height);
+    let pixel = coords + vec2<u32>(tile.offset_x, tile.offset_y);
+    if pixel.x >= image_size.x || pixel.y >= image_size.y {
+        return;
+    }
+    random_state = hash(pixel.x ^ hash(pixel.y ^ hash(tile.first_sample)));
 
+    let origin = point_to_vec3(transform_point(vec3_to_point(vec3<f32>(0.0)), camera.transform));
     let theta = tan(camera.v_fov / 2.0);
-    let aspect = f32(size.x) / f32(size.y);
-    let normalized_uv = vec2<f32>(f32(coords.x) / f32(size.x), 1.0 - (f32(coords.y) / f32(size.y))) * 2.0 - 1.0;
-    ray.direction = vec3<f32>(1.0, normalized_uv.y * theta, normalized_uv.x * aspect * theta);
-    ray.direction = normalize(point_to_vec3(transform_point(vec3_to_point(ray.direction), rotation_part_of_motor(camera.transform))));
-
-    let color = trace(ray);
-    textureStore(output_texture, coords.xy, vec4<f32>(clamp(color, vec3<f32>(0.0), vec3<f32>(1.0)), 1.0));
-}
-
-struct Point {
-    e012: f32,
-    e013: f32,
-    e023: f32,
-    e123: f32,
-}
-
-fn vec3_to_point(v: vec3<f32>) -> Point {
-    var result: Point;
-    result.e012 = v.z;
-    result.e013 = -v.y;
-    result.e023 = v.x;
-    result.e123 = 1.0;
-    return result;
-}
-
-fn point_to_vec3(p: Point) -> vec3<f32> {
-    return vec3<f32>(
-        p.e023 / p.e123,
-        -p.e013 / p.e123,
-        p.e012 / p.e123,
-    );
-}
-
-struct Motor {
-    s: f32,
-    e12: f32,
-    e13: f32,
-    e23: f32,
-    e01: f32,
-    e02: f32,
-    e03: f32,
-    e0123: f32,
-}
-
-fn rotation_part_of_motor(motor: Motor) -> Motor {
-    var result = motor;
-    result.e01 = 0.0;
-    result.e02 = 0.0;
-    result.e03 = 0.0;
-    result.e0123 = 0.0;
-    return result;
-}
-
-fn transform_point(point: Point, motor: Motor) -> Point {
-    let a = motor.s;
-    let b = motor.e12;
-    let c = motor.e13;
-    let d = motor.e23;
-    let e = motor.e01;
-    let f = motor.e02;
-    let g = motor.e03;
-    let h = motor.e0123;
-    let i = point.e012;
-    let j = point.e013;
-    let k = point.e023;
-    let l = point.e123;
-
-    var result: Point;
-    result.e012 = -2.0 * a * d * j + -2.0 * a * g * l + 1.0 * a * a * i + 2.0 * a * c * k + -1.0 * d * d * i + -2.0 * d * f * l + 2.0 * b * d * k + -2.0 * b * h * l + -2.0 * c * e * l + 1.0 * b * b * i + 2.0 * b * c * j + -1.0 * c * c * i;
-    result.e013 = -2.0 * a * b * k + -1.0 * b * b * j + 2.0 * b * c * i + 2.0 * b * e * l + 1.0 * a * a * j + 2.0 * a * d * i + 2.0 * a * f * l + -2.0 * c * h * l + -2.0 * d * g * l + -1.0 * d * d * j + 2.0 * c * d * k + 1.0 * c * c * j;
-    result.e023 = -2.0 * a * c * i + -2.0 * a * e * l + 1.0 * a * a * k + 2.0 * a * b * j + -1.0 * c * c * k + 2.0 * c * d * j + 2.0 * c * g * l + -2.0 * d * h * l + 2.0 * b * f * l + -1.0 * b * b * k + 2.0 * b * d * i + 1.0 * d * d * k;
-    result.e123 = a * a * l + b * b * l + c * c * l + d * d * l;
-    return result;
-}
-
-fn inverse_motor(motor: Motor) -> Motor {
-    var result = motor;
-    result.e12 = -motor.e12;
-    result.e13 = -motor.e13;
-    result.e23 = -motor.e23;
-    result.e01 = -motor.e01;
-    result.e02 = -motor.e02;
-    result.e03 = -motor.e03;
-    return result;
+    let aspect = f32(image_size.x) / f32(image_size.y);
+
+    var color = vec3<f32>(0.0);
+    var depth = FAR_DEPTH;
+    let samples = max(tile.samples, 1u);
+    for (var sample = 0u; sample < samples; sample += 1u) {
+        // a single sample uses the corner of the pixel, so the image doesn't shimmer
+        var offset = vec2<f32>(0.0);
+        if tile.total_samples > 1u {
+            offset = vec2<f32>(random(), random());
+        }
+        let position = vec2<f32>(pixel) + offset;
+
+        var ray: Ray;
+        ray.origin = origin;
+        // a random time while the shutter is open, so moving primitives are blurred along their path
+        ray.time = mix(camera.shutter_open, camera.shutter_close, random());
+        let normalized_uv = vec2<f32>(position.x / f32(image_size.x), 1.0 - (position.y / f32(image_size.y))) * 2.0 - 1.0;
+        ray.direction = vec3<f32>(1.0, normalized_uv.y * theta, normalized_uv.x * aspect * theta);
+        ray.direction = normalize(point_to_vec3(transform_point(vec3_to_point(ray.direction), rotation_part_of_motor(camera.transform))));
+
+        let hit = intersect_ray(ray);
+        color += trace(ray, hit);
+        if hit.hit {
+            depth = min(depth, hit.distance);
+        }
+    }
+    color /= f32(samples);
+#ifdef COUNT_INTERSECTION_TESTS
+    if camera.debug_view == DEBUG_VIEW_INTERSECTION_TESTS {
+        // without media every sample tests each primitive twice, once for the primary ray and once for the shadow ray
+        color = heatmap(f32(intersection_tests) / f32(max(primitive_count() * 2u * samples, 1u)));
+    }
+#endif
+#ifdef COUNT_RAYS
+    add_to_ray_counter(PRIMARY_RAY_COUNTER, samples);
+    add_to_ray_counter(SHADOW_RAY_COUNTER, shadow_rays);
+    add_to_ray_counter(BOUNCE_RAY_COUNTER, bounce_rays);
+    add_to_ray_counter(INTERSECTION_TEST_COUNTER, intersection_tests);
+#endif
+    textureStore(output_texture, coords, vec4<f32>(color, 1.0));
+    textureStore(depth_texture, coords, vec4<f32>(depth));
 }
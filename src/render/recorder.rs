@@ -0,0 +1,152 @@
+use crate::render::diagnostics::ReadbackBuffer;
+use bevy::{
+    ecs::system::{Commands, Res, ResMut, Resource},
+    log::{error, info},
+    time::TimeUpdateStrategy,
+};
+use std::{
+    io::Write,
+    path::PathBuf,
+    process::{Child, Command, Stdio},
+    time::Duration,
+};
+
+/// insert this resource to record the frames drawn to the window, remove it to stop recording
+///
+/// while recording, [`Time`](bevy::time::Time) advances by exactly `timestep` every update instead of by the real time,
+/// so animations play the same in every recording no matter how long a frame takes
+#[derive(Resource, Clone, Debug)]
+pub struct Recorder {
+    pub timestep: Duration,
+    /// only every nth frame is captured, 1 captures every frame
+    pub capture_every: u32,
+    /// the captured frames are written to this directory as `frame_000000.png`, `frame_000001.png` and so on
+    pub png_directory: Option<PathBuf>,
+    /// a program and its arguments that gets every captured frame as the raw rgba8 pixels shown in the window on its standard input,
+    /// for example `ffmpeg -f rawvideo -pix_fmt rgba -s 1280x720 -r 60 -i - recording.mp4`,
+    /// the size has to match the window, which should not be resized while recording
+    pub encoder: Option<Vec<String>>,
+}
+
+impl Default for Recorder {
+    fn default() -> Self {
+        Self {
+            timestep: Duration::from_secs(1) / 60,
+            capture_every: 1,
+            png_directory: Some(PathBuf::from("recording")),
+            encoder: None,
+        }
+    }
+}
+
+/// exists while a [`Recorder`] is recording
+#[derive(Resource, Default)]
+pub(super) struct RecordingState {
+    // the frames that are rendered before the next one is captured
+    frames_until_capture: u64,
+    // the frames that were written
+    captured_frames: u64,
+    encoder: Option<Child>,
+    // the main texture is copied to this buffer, with the size it was created for
+    pub(super) readback_buffer: Option<(ReadbackBuffer, (u32, u32))>,
+}
+
+impl RecordingState {
+    /// whether the next rendered frame is captured, counts the frame
+    pub(super) fn next_frame(&mut self, recorder: &Recorder) -> bool {
+        if self.frames_until_capture == 0 {
+            self.frames_until_capture = recorder.capture_every.max(1) as u64 - 1;
+            true
+        } else {
+            self.frames_until_capture -= 1;
+            false
+        }
+    }
+
+    /// writes the rgba8 pixels of a captured frame to the png directory and the encoder
+    pub(super) fn write_frame(&mut self, recorder: &Recorder, size: (u32, u32), pixels: Vec<u8>) {
+        if let Some(encoder) = &mut self.encoder {
+            let result = encoder.stdin.as_mut().unwrap().write_all(&pixels);
+            if let Err(error) = result {
+                error!("failed to write a frame to the encoder, it is not used anymore: {error}");
+                self.encoder = None;
+            }
+        }
+
+        if let Some(png_directory) = &recorder.png_directory {
+            let path = png_directory.join(format!("frame_{:06}.png", self.captured_frames));
+            let result = image::RgbaImage::from_raw(size.0, size.1, pixels)
+                .unwrap()
+                .save_with_format(&path, image::ImageFormat::Png);
+            if let Err(error) = result {
+                error!("failed to save {}: {error}", path.display());
+            }
+        }
+        self.captured_frames += 1;
+    }
+}
+
+// starts and stops recording when the recorder is added or removed, this runs before the time is updated
+pub(super) fn update_recording(
+    mut commands: Commands,
+    recorder: Option<Res<Recorder>>,
+    recording_state: Option<ResMut<RecordingState>>,
+    time_update_strategy: Option<ResMut<TimeUpdateStrategy>>,
+) {
+    match (recorder, recording_state) {
+        (Some(recorder), recording_state) => {
+            if let Some(mut time_update_strategy) = time_update_strategy {
+                *time_update_strategy = TimeUpdateStrategy::ManualDuration(recorder.timestep);
+            }
+            if recording_state.is_none() {
+                commands.insert_resource(start_recording(&recorder));
+            }
+        }
+        (None, Some(mut recording_state)) => {
+            if let Some(mut time_update_strategy) = time_update_strategy {
+                *time_update_strategy = TimeUpdateStrategy::Automatic;
+            }
+            if let Some(mut encoder) = recording_state.encoder.take() {
+                // closing its input tells the encoder that the recording ended
+                drop(encoder.stdin.take());
+                if let Err(error) = encoder.wait() {
+                    error!("failed to wait for the encoder to finish: {error}");
+                }
+            }
+            info!("recorded {} frames", recording_state.captured_frames);
+            commands.remove_resource::<RecordingState>();
+        }
+        (None, None) => {}
+    }
+}
+
+fn start_recording(recorder: &Recorder) -> RecordingState {
+    if let Some(png_directory) = &recorder.png_directory {
+        if let Err(error) = std::fs::create_dir_all(png_directory) {
+            error!("failed to create {}: {error}", png_directory.display());
+        }
+    }
+
+    let encoder = recorder
+        .encoder
+        .as_ref()
+        .and_then(|encoder| encoder.split_first())
+        .and_then(|(program, arguments)| {
+            match Command::new(program)
+                .args(arguments)
+                .stdin(Stdio::piped())
+                .spawn()
+            {
+                Ok(encoder) => Some(encoder),
+                Err(error) => {
+                    error!("failed to start the encoder {program:?}: {error}");
+                    None
+                }
+            }
+        });
+
+    RecordingState {
+        encoder,
+        ..Default::default()
+    }
+}
@@ -0,0 +1,77 @@
+use crate::render::ShaderLayoutError;
+use bevy::ecs::{event::Event, system::Resource};
+use std::fmt;
+
+/// an error of the renderer, every error is also sent as an event
+///
+/// the renderer does not stop the app on errors, it keeps trying to create itself again until it works
+#[derive(Event, Debug)]
+pub enum RenderError {
+    CreateSurface(wgpu::CreateSurfaceError),
+    /// no adapter matching the [`RenderSettings`](super::RenderSettings) can draw to the window, not even a fallback adapter
+    NoAdapter,
+    RequestDevice(wgpu::RequestDeviceError),
+    /// the window does not support an rgba8unorm format
+    UnsupportedSurface,
+    /// the gpu ran out of memory or the device was lost, the renderer is created again
+    DeviceLost(String),
+    /// an error that did not stop the renderer, usually a bug in a pipeline or render node
+    Validation(String),
+    /// a shader is invalid or a rust struct does not match its wgsl struct, which is a bug of the renderer or of a [`Primitive`](super::Primitive)
+    ShaderLayout {
+        shader: &'static str,
+        error: ShaderLayoutError,
+    },
+}
+
+impl RenderError {
+    pub(super) fn from_wgpu(error: wgpu::Error) -> Self {
+        match error {
+            wgpu::Error::OutOfMemory { .. } => RenderError::DeviceLost(error.to_string()),
+            // wgpu reports a lost device as a validation error of whatever used it
+            wgpu::Error::Validation { description, .. }
+                if description.contains("device is lost") =>
+            {
+                RenderError::DeviceLost(description)
+            }
+            wgpu::Error::Validation { description, .. } => RenderError::Validation(description),
+        }
+    }
+}
+
+impl fmt::Display for RenderError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            RenderError::CreateSurface(error) => {
+                write!(f, "failed to create the window surface: {error}")
+            }
+            RenderError::NoAdapter => write!(f, "no gpu adapter can draw to the window"),
+            RenderError::RequestDevice(error) => {
+                write!(f, "failed to create the gpu device: {error}")
+            }
+            RenderError::UnsupportedSurface => {
+                write!(f, "the window does not support an rgba8unorm format")
+            }
+            RenderError::DeviceLost(message) => write!(f, "the gpu device was lost: {message}"),
+            RenderError::Validation(message) => write!(f, "gpu validation error: {message}"),
+            RenderError::ShaderLayout { shader, error } => {
+                write!(f, "{shader}: {error}")
+            }
+        }
+    }
+}
+
+impl std::error::Error for RenderError {}
+
+/// whether the renderer is drawing frames
+#[derive(Resource, Clone, Debug, Default, PartialEq, Eq)]
+pub enum RenderStatus {
+    /// the renderer could not be created yet, or it lost its device and is created again
+    #[default]
+    Unavailable,
+    Running {
+        adapter: wgpu::AdapterInfo,
+        /// whether a lower capability adapter is used, because the preferred one could not be used
+        fallback_adapter: bool,
+    },
+}
@@ -0,0 +1,725 @@
+use crate::render::diagnostics::MAX_TIMED_PASSES;
+use bevy::ecs::{system::Resource, world::World};
+use std::{collections::HashMap, fmt};
+
+/// the rgba8unorm texture that is copied to the window at the end of the frame
+pub const MAIN_TEXTURE: &str = "main";
+/// the rgba16float texture the ray tracer writes the lit image into, before post processing,
+/// it is [`TextureSize::Scaled`] and gets upscaled to the main texture by the post process node
+pub const HDR_TEXTURE: &str = "hdr";
+/// the [`TextureSize::Scaled`] r32float texture the ray tracer writes the distance to the closest hit into
+pub const DEPTH_TEXTURE: &str = "depth";
+
+pub const RAY_TRACING_NODE: &str = "ray_tracing";
+pub const POST_PROCESS_NODE: &str = "post_process";
+pub const GIZMO_NODE: &str = "gizmos";
+pub const OVERLAY_NODE: &str = "overlay";
+/// copies [`MAIN_TEXTURE`] to the window, the nodes that write the main texture and were added before it run before it,
+/// nodes added later need an edge to it
+pub const PRESENT_NODE: &str = "present";
+
+/// a pass that records gpu commands each frame
+///
+/// the writes to a resource happen in the order given with [`RenderGraph::add_node_edge`] and otherwise in the order the nodes were added in,
+/// a node that reads a resource runs after the last write of a node that was added before it or that runs before it because of an edge,
+/// so a node can read and write a resource between two other nodes that write it
+pub trait RenderNode: Send + Sync + 'static {
+    /// the names of the graph resources this node reads from
+    fn reads(&self) -> Vec<&'static str> {
+        vec![]
+    }
+
+    /// the names of the graph resources this node writes to, including ones it also reads from
+    fn writes(&self) -> Vec<&'static str> {
+        vec![]
+    }
+
+    /// called before the first run and every time the graph resources were recreated, for example after the window was resized
+    /// or after the renderer was created again because the gpu device was lost,
+    /// so bind groups and other gpu objects should be created here
+    fn prepare(
+        &mut self,
+        _device: &wgpu::Device,
+        _resources: &RenderGraphResources,
+        _world: &World,
+    ) {
+    }
+
+    fn run(&mut self, context: &mut RenderContext<'_>, world: &World);
+}
+
+pub struct RenderContext<'a> {
+    pub device: &'a wgpu::Device,
+    pub queue: &'a wgpu::Queue,
+    pub encoder: &'a mut wgpu::CommandEncoder,
+    pub resources: &'a RenderGraphResources,
+    /// the texture of the window that gets presented after every node ran
+    pub surface_texture: &'a wgpu::Texture,
+    // the query set passes write their timestamps to, `None` when no passes are timed this frame
+    query_set: Option<&'a wgpu::QuerySet>,
+    // the node that is running and the node of every pass that was timed this frame
+    node: &'static str,
+    timed_passes: Vec<&'static str>,
+}
+
+impl<'a> RenderContext<'a> {
+    /// the timestamp writes for a compute pass of the running node, so its gpu time is added to [`RenderDiagnostics`](super::RenderDiagnostics),
+    /// `None` when the adapter does not support timestamp queries or no pass is timed this frame
+    pub fn compute_pass_timestamp_writes(
+        &mut self,
+    ) -> Option<wgpu::ComputePassTimestampWrites<'a>> {
+        let (query_set, index) = self.next_timestamp_queries()?;
+        Some(wgpu::ComputePassTimestampWrites {
+            query_set,
+            beginning_of_pass_write_index: Some(index),
+            end_of_pass_write_index: Some(index + 1),
+        })
+    }
+
+    /// like [`RenderContext::compute_pass_timestamp_writes`], but for a render pass
+    pub fn render_pass_timestamp_writes(&mut self) -> Option<wgpu::RenderPassTimestampWrites<'a>> {
+        let (query_set, index) = self.next_timestamp_queries()?;
+        Some(wgpu::RenderPassTimestampWrites {
+            query_set,
+            beginning_of_pass_write_index: Some(index),
+            end_of_pass_write_index: Some(index + 1),
+        })
+    }
+
+    fn next_timestamp_queries(&mut self) -> Option<(&'a wgpu::QuerySet, u32)> {
+        let query_set = self.query_set?;
+        if self.timed_passes.len() as u32 >= MAX_TIMED_PASSES {
+            return None;
+        }
+        self.timed_passes.push(self.node);
+        Some((query_set, (self.timed_passes.len() as u32 - 1) * 2))
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum TextureSize {
+    /// the size of the window, the texture is recreated when the window is resized
+    Surface,
+    /// the size of the window multiplied by the render scale, the texture is recreated when either changes
+    Scaled,
+    Fixed {
+        width: u32,
+        height: u32,
+    },
+}
+
+#[derive(Clone, Copy, Debug)]
+pub struct GraphTexture {
+    pub size: TextureSize,
+    pub format: wgpu::TextureFormat,
+    pub usage: wgpu::TextureUsages,
+}
+
+#[derive(Clone, Copy, Debug)]
+pub struct GraphBuffer {
+    pub size: wgpu::BufferAddress,
+    pub usage: wgpu::BufferUsages,
+}
+
+/// the textures and buffers that were declared in the [`RenderGraph`]
+pub struct RenderGraphResources {
+    textures: HashMap<&'static str, (GraphTexture, Option<wgpu::Texture>)>,
+    buffers: HashMap<&'static str, (GraphBuffer, Option<wgpu::Buffer>)>,
+    surface_size: (u32, u32),
+    render_scale: f32,
+    scaled_size: (u32, u32),
+}
+
+impl RenderGraphResources {
+    /// panics if there is no texture with this name
+    pub fn texture(&self, name: &str) -> &wgpu::Texture {
+        self.textures
+            .get(name)
+            .and_then(|(_, texture)| texture.as_ref())
+            .unwrap_or_else(|| panic!("the render graph has no texture called {name:?}"))
+    }
+
+    /// panics if there is no buffer with this name
+    pub fn buffer(&self, name: &str) -> &wgpu::Buffer {
+        self.buffers
+            .get(name)
+            .and_then(|(_, buffer)| buffer.as_ref())
+            .unwrap_or_else(|| panic!("the render graph has no buffer called {name:?}"))
+    }
+
+    pub fn surface_size(&self) -> (u32, u32) {
+        self.surface_size
+    }
+
+    /// the size of the [`TextureSize::Scaled`] textures
+    pub fn scaled_size(&self) -> (u32, u32) {
+        self.scaled_size
+    }
+
+    /// how much the window size is multiplied by for the [`TextureSize::Scaled`] textures
+    pub fn render_scale(&self) -> f32 {
+        self.render_scale
+    }
+
+    fn contains(&self, name: &str) -> bool {
+        self.textures.contains_key(name) || self.buffers.contains_key(name)
+    }
+
+    // creates the resources that are missing and recreates the ones that depend on the surface size or the render scale,
+    // returns whether any resource was created
+    fn create(&mut self, device: &wgpu::Device, surface_size: (u32, u32)) -> bool {
+        let scaled_size = (
+            ((surface_size.0 as f32 * self.render_scale).round() as u32).max(1),
+            ((surface_size.1 as f32 * self.render_scale).round() as u32).max(1),
+        );
+        let resized = self.surface_size != surface_size;
+        let rescaled = self.scaled_size != scaled_size;
+        self.surface_size = surface_size;
+        self.scaled_size = scaled_size;
+
+        let mut created = false;
+        for (&name, (descriptor, texture)) in &mut self.textures {
+            let outdated = match descriptor.size {
+                TextureSize::Surface => resized,
+                TextureSize::Scaled => rescaled,
+                TextureSize::Fixed { .. } => false,
+            };
+            if texture.is_some() && !outdated {
+                continue;
+            }
+
+            let (width, height) = match descriptor.size {
+                TextureSize::Surface => surface_size,
+                TextureSize::Scaled => scaled_size,
+                TextureSize::Fixed { width, height } => (width, height),
+            };
+            *texture = Some(device.create_texture(&wgpu::TextureDescriptor {
+                label: Some(name),
+                size: wgpu::Extent3d {
+                    width,
+                    height,
+                    depth_or_array_layers: 1,
+                },
+                mip_level_count: 1,
+                sample_count: 1,
+                dimension: wgpu::TextureDimension::D2,
+                format: descriptor.format,
+                usage: descriptor.usage,
+                view_formats: &[],
+            }));
+            created = true;
+        }
+        for (&name, (descriptor, buffer)) in &mut self.buffers {
+            if buffer.is_none() {
+                *buffer = Some(device.create_buffer(&wgpu::BufferDescriptor {
+                    label: Some(name),
+                    size: descriptor.size,
+                    usage: descriptor.usage,
+                    mapped_at_creation: false,
+                }));
+                created = true;
+            }
+        }
+        created
+    }
+}
+
+struct NodeEntry {
+    name: &'static str,
+    node: Box<dyn RenderNode>,
+    reads: Vec<&'static str>,
+    writes: Vec<&'static str>,
+    prepared: bool,
+}
+
+/// the passes that are recorded every frame and the resources they share
+///
+/// plugins can add their own passes by getting this resource and adding nodes to it:
+/// ```ignore
+/// let mut render_graph = app.world.resource_mut::<RenderGraph>();
+/// render_graph.add_node("outline", OutlineNode::default())?;
+/// render_graph.add_node_edge(POST_PROCESS_NODE, "outline")?;
+/// render_graph.add_node_edge("outline", GIZMO_NODE)?;
+/// ```
+#[derive(Resource)]
+pub struct RenderGraph {
+    resources: RenderGraphResources,
+    nodes: Vec<NodeEntry>,
+    edges: Vec<(usize, usize)>,
+    // indices into `nodes` in the order they are run
+    order: Vec<usize>,
+}
+
+impl RenderGraph {
+    pub(super) fn new() -> Self {
+        Self {
+            resources: RenderGraphResources {
+                textures: HashMap::new(),
+                buffers: HashMap::new(),
+                surface_size: (0, 0),
+                render_scale: 1.0,
+                scaled_size: (0, 0),
+            },
+            nodes: vec![],
+            edges: vec![],
+            order: vec![],
+        }
+    }
+
+    pub fn add_texture(
+        &mut self,
+        name: &'static str,
+        texture: GraphTexture,
+    ) -> Result<(), RenderGraphError> {
+        if self.resources.contains(name) {
+            return Err(RenderGraphError::DuplicateResource(name));
+        }
+        self.resources.textures.insert(name, (texture, None));
+        Ok(())
+    }
+
+    pub fn add_buffer(
+        &mut self,
+        name: &'static str,
+        buffer: GraphBuffer,
+    ) -> Result<(), RenderGraphError> {
+        if self.resources.contains(name) {
+            return Err(RenderGraphError::DuplicateResource(name));
+        }
+        self.resources.buffers.insert(name, (buffer, None));
+        Ok(())
+    }
+
+    /// adds a node that reads and writes resources that were already added to the graph
+    pub fn add_node(
+        &mut self,
+        name: &'static str,
+        node: impl RenderNode,
+    ) -> Result<(), RenderGraphError> {
+        if self.node_index(name).is_some() {
+            return Err(RenderGraphError::DuplicateNode(name));
+        }
+
+        let (reads, writes) = (node.reads(), node.writes());
+        if let Some(&resource) = reads
+            .iter()
+            .chain(&writes)
+            .find(|resource| !self.resources.contains(resource))
+        {
+            return Err(RenderGraphError::UnknownResource {
+                node: name,
+                resource,
+            });
+        }
+
+        self.nodes.push(NodeEntry {
+            name,
+            node: Box::new(node),
+            reads,
+            writes,
+            prepared: false,
+        });
+        if let Err(error) = self.sort() {
+            self.nodes.pop();
+            return Err(error);
+        }
+        Ok(())
+    }
+
+    /// makes `before` run before `after`
+    pub fn add_node_edge(
+        &mut self,
+        before: &'static str,
+        after: &'static str,
+    ) -> Result<(), RenderGraphError> {
+        let before = self
+            .node_index(before)
+            .ok_or(RenderGraphError::UnknownNode(before))?;
+        let after = self
+            .node_index(after)
+            .ok_or(RenderGraphError::UnknownNode(after))?;
+
+        self.edges.push((before, after));
+        if let Err(error) = self.sort() {
+            self.edges.pop();
+            return Err(error);
+        }
+        Ok(())
+    }
+
+    /// removes a node and its edges and returns it, for example to replace one of the builtin nodes
+    pub fn remove_node(
+        &mut self,
+        name: &'static str,
+    ) -> Result<Box<dyn RenderNode>, RenderGraphError> {
+        let index = self
+            .node_index(name)
+            .ok_or(RenderGraphError::UnknownNode(name))?;
+
+        let edges = self.edges.clone();
+        let entry = self.nodes.remove(index);
+        self.edges
+            .retain(|&(before, after)| before != index && after != index);
+        for (before, after) in &mut self.edges {
+            *before -= (*before > index) as usize;
+            *after -= (*after > index) as usize;
+        }
+        if let Err(error) = self.sort() {
+            // without the node a reader can depend on an earlier write that has to run after it
+            self.nodes.insert(index, entry);
+            self.edges = edges;
+            self.sort().unwrap();
+            return Err(error);
+        }
+        Ok(entry.node)
+    }
+
+    /// the names of the nodes in the order they are run
+    pub fn node_order(&self) -> impl Iterator<Item = &'static str> + '_ {
+        self.order.iter().map(|&index| self.nodes[index].name)
+    }
+
+    pub fn resources(&self) -> &RenderGraphResources {
+        &self.resources
+    }
+
+    /// the [`TextureSize::Scaled`] textures are recreated with the new scale when the graph is prepared
+    pub(super) fn set_render_scale(&mut self, render_scale: f32) {
+        self.resources.render_scale = render_scale;
+    }
+
+    fn node_index(&self, name: &str) -> Option<usize> {
+        self.nodes.iter().position(|node| node.name == name)
+    }
+
+    // orders the nodes by their edges and resources, nodes that could run in any order keep the order they were added in
+    //
+    // the writes to a resource happen in the order the edges and the order the nodes were added in give,
+    // a node that reads it runs after the last write of a node that was added before it or runs before it because of the edges,
+    // and the later writes run after the node that reads it
+    fn sort(&mut self) -> Result<(), RenderGraphError> {
+        let count = self.nodes.len();
+        let mut dependencies = vec![vec![]; count];
+        for &(before, after) in &self.edges {
+            dependencies[after].push(before);
+        }
+
+        let sequence = self.topological_order(&dependencies)?;
+        let mut position = vec![0; count];
+        for (index, &node) in sequence.iter().enumerate() {
+            position[node] = index;
+        }
+        // `runs_before[a][b]` when the edges make `a` run before `b`
+        let mut runs_before = vec![vec![false; count]; count];
+        for &node in &sequence {
+            for &dependency in &dependencies[node] {
+                runs_before[dependency][node] = true;
+                for row in &mut runs_before {
+                    row[node] |= row[dependency];
+                }
+            }
+        }
+
+        let mut resource_dependencies = vec![];
+        for (reader, reader_node) in self.nodes.iter().enumerate() {
+            for resource in &reader_node.reads {
+                let writers = (0..count).filter(|&writer| {
+                    writer != reader && self.nodes[writer].writes.contains(resource)
+                });
+                let last_write = writers
+                    .clone()
+                    .filter(|&writer| {
+                        (writer < reader || runs_before[writer][reader])
+                            && !runs_before[reader][writer]
+                    })
+                    .max_by_key(|&writer| position[writer]);
+                if let Some(last_write) = last_write {
+                    resource_dependencies.push((last_write, reader));
+                }
+                for writer in writers {
+                    let later = match last_write {
+                        Some(last_write) => position[writer] > position[last_write],
+                        None => true,
+                    };
+                    if later && !runs_before[writer][reader] {
+                        resource_dependencies.push((reader, writer));
+                    }
+                }
+            }
+        }
+        for (before, after) in resource_dependencies {
+            dependencies[after].push(before);
+        }
+
+        self.order = self.topological_order(&dependencies)?;
+        Ok(())
+    }
+
+    // the nodes in an order where each runs after its dependencies, preferring the nodes that were added first
+    fn topological_order(
+        &self,
+        dependencies: &[Vec<usize>],
+    ) -> Result<Vec<usize>, RenderGraphError> {
+        let mut order = Vec::with_capacity(self.nodes.len());
+        let mut done = vec![false; self.nodes.len()];
+        while order.len() < self.nodes.len() {
+            let Some(next) = (0..self.nodes.len()).find(|&index| {
+                !done[index]
+                    && dependencies[index]
+                        .iter()
+                        .all(|&dependency| done[dependency])
+            }) else {
+                return Err(RenderGraphError::Cycle(
+                    (0..self.nodes.len())
+                        .filter(|&index| !done[index])
+                        .map(|index| self.nodes[index].name)
+                        .collect(),
+                ));
+            };
+            done[next] = true;
+            order.push(next);
+        }
+        Ok(order)
+    }
+
+    /// creates the resources for the current surface size and prepares the nodes that need it
+    pub(super) fn prepare(
+        &mut self,
+        device: &wgpu::Device,
+        surface_size: (u32, u32),
+        world: &World,
+    ) {
+        if self.resources.create(device, surface_size) {
+            for node in &mut self.nodes {
+                node.prepared = false;
+            }
+        }
+
+        for node in &mut self.nodes {
+            if !node.prepared {
+                node.node.prepare(device, &self.resources, world);
+                node.prepared = true;
+            }
+        }
+    }
+
+    /// drops every resource and prepares every node again on the next frame, for when the renderer was created again with a new device
+    pub(super) fn reset(&mut self) {
+        for (_, texture) in self.resources.textures.values_mut() {
+            *texture = None;
+        }
+        for (_, buffer) in self.resources.buffers.values_mut() {
+            *buffer = None;
+        }
+        for node in &mut self.nodes {
+            node.prepared = false;
+        }
+    }
+
+    /// runs every node and returns the node of every pass that wrote timestamps to `query_set`, in the order of their queries
+    pub(super) fn run(
+        &mut self,
+        device: &wgpu::Device,
+        queue: &wgpu::Queue,
+        encoder: &mut wgpu::CommandEncoder,
+        surface_texture: &wgpu::Texture,
+        query_set: Option<&wgpu::QuerySet>,
+        world: &World,
+    ) -> Vec<&'static str> {
+        let mut context = RenderContext {
+            device,
+            queue,
+            encoder,
+            resources: &self.resources,
+            surface_texture,
+            query_set,
+            node: "",
+            timed_passes: vec![],
+        };
+        for &index in &self.order {
+            context.node = self.nodes[index].name;
+            self.nodes[index].node.run(&mut context, world);
+        }
+        context.timed_passes
+    }
+}
+
+#[derive(Debug)]
+pub enum RenderGraphError {
+    DuplicateNode(&'static str),
+    DuplicateResource(&'static str),
+    UnknownNode(&'static str),
+    UnknownResource {
+        node: &'static str,
+        resource: &'static str,
+    },
+    /// the nodes that could not be ordered
+    Cycle(Vec<&'static str>),
+}
+
+impl fmt::Display for RenderGraphError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            RenderGraphError::DuplicateNode(name) => {
+                write!(f, "the render graph already has a node called {name:?}")
+            }
+            RenderGraphError::DuplicateResource(name) => {
+                write!(f, "the render graph already has a resource called {name:?}")
+            }
+            RenderGraphError::UnknownNode(name) => {
+                write!(f, "the render graph has no node called {name:?}")
+            }
+            RenderGraphError::UnknownResource { node, resource } => write!(
+                f,
+                "node {node:?} uses {resource:?} which was not added to the render graph"
+            ),
+            RenderGraphError::Cycle(nodes) => {
+                write!(f, "the render graph nodes {nodes:?} depend on each other")
+            }
+        }
+    }
+}
+
+impl std::error::Error for RenderGraphError {}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    struct TestNode {
+        reads: Vec<&'static str>,
+        writes: Vec<&'static str>,
+    }
+
+    impl RenderNode for TestNode {
+        fn reads(&self) -> Vec<&'static str> {
+            self.reads.clone()
+        }
+
+        fn writes(&self) -> Vec<&'static str> {
+            self.writes.clone()
+        }
+
+        fn run(&mut self, _context: &mut RenderContext<'_>, _world: &World) {}
+    }
+
+    fn node(reads: &[&'static str], writes: &[&'static str]) -> TestNode {
+        TestNode {
+            reads: reads.to_vec(),
+            writes: writes.to_vec(),
+        }
+    }
+
+    fn graph(resources: &[&'static str]) -> RenderGraph {
+        let mut render_graph = RenderGraph::new();
+        for &resource in resources {
+            render_graph
+                .add_buffer(
+                    resource,
+                    GraphBuffer {
+                        size: 4,
+                        usage: wgpu::BufferUsages::STORAGE,
+                    },
+                )
+                .unwrap();
+        }
+        render_graph
+    }
+
+    fn order(render_graph: &RenderGraph) -> Vec<&'static str> {
+        render_graph.node_order().collect()
+    }
+
+    #[test]
+    fn reads_run_after_the_last_write_before_them() {
+        let mut render_graph = graph(&["x"]);
+        render_graph.add_node("write", node(&[], &["x"])).unwrap();
+        render_graph.add_node("read", node(&["x"], &[])).unwrap();
+        render_graph
+            .add_node("overwrite", node(&[], &["x"]))
+            .unwrap();
+        render_graph
+            .add_node("read_again", node(&["x"], &[]))
+            .unwrap();
+        assert_eq!(
+            order(&render_graph),
+            ["write", "read", "overwrite", "read_again"]
+        );
+
+        // the edge makes `insert` the last write before `read`, so `read` no longer has to run before `overwrite`
+        render_graph.add_node("insert", node(&[], &["x"])).unwrap();
+        render_graph.add_node_edge("insert", "read").unwrap();
+        assert_eq!(
+            order(&render_graph),
+            ["write", "overwrite", "read_again", "insert", "read"]
+        );
+    }
+
+    #[test]
+    fn unknown_resources_and_duplicate_nodes_are_rejected() {
+        let mut render_graph = graph(&["x"]);
+        render_graph.add_node("write", node(&[], &["x"])).unwrap();
+        assert!(matches!(
+            render_graph.add_node("write", node(&[], &["x"])),
+            Err(RenderGraphError::DuplicateNode("write"))
+        ));
+        assert!(matches!(
+            render_graph.add_node("read", node(&["y"], &[])),
+            Err(RenderGraphError::UnknownResource {
+                node: "read",
+                resource: "y"
+            })
+        ));
+        assert_eq!(order(&render_graph), ["write"]);
+    }
+
+    #[test]
+    fn cycles_are_rejected() {
+        let mut render_graph = graph(&[]);
+        for name in ["a", "b", "c"] {
+            render_graph.add_node(name, node(&[], &[])).unwrap();
+        }
+        render_graph.add_node_edge("b", "a").unwrap();
+        render_graph.add_node_edge("c", "b").unwrap();
+        assert_eq!(order(&render_graph), ["c", "b", "a"]);
+        match render_graph.add_node_edge("a", "c") {
+            Err(RenderGraphError::Cycle(mut nodes)) => {
+                nodes.sort();
+                assert_eq!(nodes, ["a", "b", "c"]);
+            }
+            result => panic!("expected a cycle, got {result:?}"),
+        }
+
+        // the edge that failed is not kept
+        assert_eq!(order(&render_graph), ["c", "b", "a"]);
+        render_graph.add_node("d", node(&[], &[])).unwrap();
+        render_graph.add_node_edge("d", "c").unwrap();
+        assert_eq!(order(&render_graph), ["d", "c", "b", "a"]);
+    }
+
+    #[test]
+    fn a_failed_removal_keeps_the_node() {
+        let mut render_graph = graph(&["x", "y"]);
+        render_graph.add_node("write_y", node(&[], &["y"])).unwrap();
+        render_graph
+            .add_node("write_x", node(&["y"], &["x"]))
+            .unwrap();
+        render_graph
+            .add_node("overwrite_x", node(&[], &["x"]))
+            .unwrap();
+        render_graph.add_node("read_x", node(&["x"], &[])).unwrap();
+        render_graph.add_node_edge("read_x", "write_y").unwrap();
+        let before = order(&render_graph);
+        assert_eq!(before, ["overwrite_x", "read_x", "write_y", "write_x"]);
+
+        // without it, `read_x` reads the write of `write_x`, which runs after it because of `write_y`
+        assert!(matches!(
+            render_graph.remove_node("overwrite_x"),
+            Err(RenderGraphError::Cycle(_))
+        ));
+        assert_eq!(order(&render_graph), before);
+
+        render_graph.remove_node("read_x").unwrap();
+        assert_eq!(order(&render_graph), ["write_y", "write_x", "overwrite_x"]);
+    }
+}
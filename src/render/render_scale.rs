@@ -0,0 +1,36 @@
+use bevy::ecs::system::Resource;
+
+/// how large the image is ray traced compared to the window, it is upscaled to the window after post processing
+///
+/// ray tracing costs about the same for every pixel, so a scale of 0.5 ray traces a quarter of the pixels
+#[derive(Resource, Clone, Copy, Debug, PartialEq)]
+pub enum RenderScale {
+    /// values above 1 render more pixels than the window has and scale them down
+    Fixed(f32),
+    /// lowers the scale while frames take longer than `target_frame_time` and raises it again while they are faster,
+    /// with vsync frames never take less than the refresh interval, so the target should be above it
+    ///
+    /// the frame time is the smoothed [`FrameTimeDiagnosticsPlugin::FRAME_TIME`](bevy::diagnostic::FrameTimeDiagnosticsPlugin::FRAME_TIME),
+    /// which is the recorder's timestep while recording
+    Adaptive {
+        /// in milliseconds
+        target_frame_time: f32,
+        min_scale: f32,
+        max_scale: f32,
+    },
+}
+
+impl Default for RenderScale {
+    fn default() -> Self {
+        Self::Fixed(1.0)
+    }
+}
+
+/// how the ray traced image is upscaled to the window when the [`RenderScale`] is not 1
+#[derive(Resource, Clone, Copy, Debug, Default, PartialEq, Eq, Hash)]
+pub enum UpscaleFilter {
+    #[default]
+    Bilinear,
+    /// bilinear, but pixels that are much brighter or darker than the closest one are mostly ignored, so edges stay sharp
+    EdgeAware,
+}
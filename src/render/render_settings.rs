@@ -0,0 +1,34 @@
+use bevy::ecs::system::Resource;
+
+/// how the renderer picks its gpu, insert this resource before adding the `RenderPlugin` to change it
+///
+/// the settings are used whenever the renderer is created, also after the device was lost,
+/// only `present_mode` is applied while the renderer is running
+#[derive(Resource, Clone, Debug)]
+pub struct RenderSettings {
+    pub backends: wgpu::Backends,
+    pub power_preference: wgpu::PowerPreference,
+    /// use the first adapter whose name contains this instead of choosing one by `power_preference`
+    pub adapter_name: Option<String>,
+    /// only use a software adapter
+    pub force_fallback_adapter: bool,
+    /// `AutoVsync` is used instead of vsync modes and `AutoNoVsync` instead of other modes the window does not support
+    pub present_mode: wgpu::PresentMode,
+    pub features: wgpu::Features,
+    /// when these are the default limits, adapters that do not support them are tried with the downlevel limits
+    pub limits: wgpu::Limits,
+}
+
+impl Default for RenderSettings {
+    fn default() -> Self {
+        Self {
+            backends: wgpu::Backends::all(),
+            power_preference: wgpu::PowerPreference::HighPerformance,
+            adapter_name: None,
+            force_fallback_adapter: false,
+            present_mode: wgpu::PresentMode::AutoNoVsync,
+            features: wgpu::Features::empty(),
+            limits: wgpu::Limits::default(),
+        }
+    }
+}
@@ -1,18 +1,86 @@
 use crate::{
-    math::{Motor, Vector3},
-    render::{Camera, MainCamera, Material, Sphere},
-    transform::GlobalTransform,
+    math::{Motor, Vector2, Vector3},
+    render::{
+        compose_shader,
+        diagnostics::{GpuTimer, RayCounters, ReadbackBuffer},
+        gizmos::{GizmoBuffer, GizmoLine},
+        gpu_array_buffer::GpuArrayBuffer,
+        gpu_slot_buffer::GpuSlotBuffer,
+        post_process::{
+            PostProcessPipelines, PostProcessTargets, HDR_TEXTURE_FORMAT, MAIN_TEXTURE_FORMAT,
+        },
+        recorder::{Recorder, RecordingState},
+        render_graph::{
+            GraphTexture, RenderContext, RenderGraph, RenderGraphResources, RenderNode,
+            TextureSize, DEPTH_TEXTURE, GIZMO_NODE, HDR_TEXTURE, MAIN_TEXTURE, OVERLAY_NODE,
+            POST_PROCESS_NODE, PRESENT_NODE, RAY_TRACING_NODE,
+        },
+        shader::ShaderFiles,
+        tiled_render::{
+            f16_to_f32, f32_to_f16, RenderedImage, TileProgress, TiledRender, TiledRenderError,
+        },
+        validate_struct_layouts, Camera, ColorGrading, CountRays, DebugView, Fog, Lut3d,
+        MainCamera, MaterialHandle, Materials, MediaRendering, OverlayRect, OverlayText,
+        PostProcess, Primitive, RenderDiagnostics, RenderError, RenderScale, RenderSettings,
+        RenderStatus, ShaderComposeError, ShaderHotReload, ShaderLayoutError, ShaderStruct,
+        StructLayout, Tonemapping, UpscaleFilter,
+    },
+    transform::{GlobalTransform, PreviousGlobalTransform},
     window::InitWindowResource,
 };
-use bevy::ecs::{
-    change_detection::DetectChanges,
-    system::{Query, Res, ResMut, Resource},
-    world::{FromWorld, Ref, World},
+use bevy::{
+    diagnostic::{Diagnostics, DiagnosticsStore, FrameTimeDiagnosticsPlugin},
+    ecs::{
+        change_detection::{DetectChanges, DetectChangesMut},
+        entity::Entity,
+        query::With,
+        system::{Local, Query, Res, ResMut, Resource},
+        world::{FromWorld, Mut, Ref, World},
+    },
+    log::{error, info_span, warn},
 };
 use encase::{ArrayLength, ShaderSize, ShaderType, StorageBuffer, UniformBuffer};
-use std::sync::Arc;
+use std::{
+    any::{Any, TypeId},
+    sync::{
+        atomic::{AtomicU64, Ordering},
+        Arc, Mutex,
+    },
+    time::{Duration, Instant},
+};
+use wgpu::util::DeviceExt;
 use winit::window::Window;
 
+// checks the rust structs against the wgsl structs of the shaders that don't depend on the registered primitives,
+// a mismatch would only show up as garbage on screen
+fn validate_shaders() -> Result<(), RenderError> {
+    let gizmo_source = compose_shader(include_str!("./gizmos.wgsl"), &[]).unwrap();
+    for (shader, source, structs) in [
+        (
+            "overlay.wgsl",
+            include_str!("./overlay.wgsl"),
+            vec![("OverlayQuad", GpuOverlayQuad::struct_layout())],
+        ),
+        (
+            "gizmos.wgsl",
+            &gizmo_source,
+            vec![
+                ("Camera", GpuCamera::struct_layout()),
+                ("GizmoVertex", GpuGizmoVertex::struct_layout()),
+            ],
+        ),
+        (
+            "post_process.wgsl",
+            include_str!("./post_process.wgsl"),
+            vec![("PostProcess", GpuPostProcess::struct_layout())],
+        ),
+    ] {
+        validate_struct_layouts(source, &structs)
+            .map_err(|error| RenderError::ShaderLayout { shader, error })?;
+    }
+    Ok(())
+}
+
 #[derive(ShaderType)]
 struct GpuCamera {
     transform: Motor,
@@ -20,38 +88,125 @@ struct GpuCamera {
     min_distance: f32,
     max_distance: f32,
     sun_direction: Vector3,
+    debug_view: u32,
+    shutter_open: f32,
+    shutter_close: f32,
+    fog_albedo: Vector3,
+    fog_density: f32,
+    fog_height: f32,
+    fog_height_falloff: f32,
+    fog_anisotropy: f32,
+    media_rendering: u32,
+    max_bounces: u32,
 }
 
 #[derive(ShaderType)]
-struct GpuSphere {
-    transform: Motor,
+struct GpuRenderTile {
+    offset_x: u32,
+    offset_y: u32,
+    image_width: u32,
+    image_height: u32,
+    samples: u32,
+    first_sample: u32,
+    total_samples: u32,
+}
+
+#[derive(ShaderType)]
+struct GpuMaterial {
+    color: Vector3,
+    emission: Vector3,
+}
+
+#[derive(ShaderType)]
+struct GpuPostProcess {
+    bloom_threshold: f32,
+    bloom_intensity: f32,
+    chromatic_aberration_intensity: f32,
+    vignette_intensity: f32,
+    vignette_radius: f32,
+    vignette_smoothness: f32,
+    film_grain_intensity: f32,
+    frame: u32,
+    exposure: f32,
+    contrast: f32,
+    saturation: f32,
+    tonemapping: u32,
+    white_balance: Vector3,
+    lift: Vector3,
+    gamma: Vector3,
+    gain: Vector3,
+    lut_domain_min: Vector3,
+    lut_domain_max: Vector3,
+}
+
+#[derive(ShaderType)]
+pub(super) struct GpuGizmoVertex {
+    position: Vector3,
+    color: Vector3,
+}
+
+// the builtin font has 128 8x8 glyphs, they are laid out in rows of 16 in the font atlas
+const FONT_GLYPH_SIZE: u32 = 8;
+const FONT_ATLAS_COLUMNS: u32 = 16;
+const FONT_ATLAS_WIDTH: u32 = FONT_ATLAS_COLUMNS * FONT_GLYPH_SIZE;
+const FONT_ATLAS_HEIGHT: u32 = 128 / FONT_ATLAS_COLUMNS * FONT_GLYPH_SIZE;
+
+// overlay quads with this glyph are drawn as solid rectangles
+const OVERLAY_SOLID_GLYPH: u32 = u32::MAX;
+
+#[derive(ShaderType)]
+struct GpuOverlayQuad {
+    position: Vector2,
+    size: Vector2,
     color: Vector3,
-    radius: f32,
+    glyph: u32,
 }
 
 #[derive(ShaderType)]
-struct GpuSpheres<'a> {
+struct GpuOverlayQuads<'a> {
+    screen_size: Vector2,
     length: ArrayLength,
     #[size(runtime)]
-    data: &'a [GpuSphere],
+    data: &'a [GpuOverlayQuad],
 }
 
 #[derive(Resource)]
 pub(super) struct RenderState {
-    ray_tracing_pipeline: wgpu::ComputePipeline,
+    overlay_pipeline: wgpu::RenderPipeline,
+
+    overlay_bind_group_layout: wgpu::BindGroupLayout,
+    post_process_bind_group_layout: wgpu::BindGroupLayout,
 
-    sphere_bind_group_layout: wgpu::BindGroupLayout,
+    post_process_pipelines: PostProcessPipelines,
+
+    font_atlas_bind_group: wgpu::BindGroup,
 
     camera_bind_group: wgpu::BindGroup,
+    camera_bind_group_layout: wgpu::BindGroupLayout,
     camera_uniform_buffer: wgpu::Buffer,
 
-    main_texture_bind_group: wgpu::BindGroup,
-    main_texture_bind_group_layout: wgpu::BindGroupLayout,
-    main_texture: wgpu::Texture,
+    output_bind_group_layout: wgpu::BindGroupLayout,
+    depth_texture_bind_group_layout: wgpu::BindGroupLayout,
 
     queue: wgpu::Queue,
     device: wgpu::Device,
 
+    // the errors wgpu reported since the last frame
+    errors: Arc<Mutex<Vec<RenderError>>>,
+    adapter_info: wgpu::AdapterInfo,
+    fallback_adapter: bool,
+    gpu_timer: Option<GpuTimer>,
+    ray_counters: RayCounters,
+    // the frames submitted since the renderer was created, the readbacks of diagnostics are tagged with it
+    frames: u64,
+    // the frame and the gpu time in milliseconds of the last timed ray tracing pass
+    ray_tracing_time: Option<(u64, f64)>,
+    // bytes written to buffers since the diagnostics were measured, the systems that upload run in parallel
+    uploaded_bytes: AtomicU64,
+    surface_configurations: u64,
+    // the present modes the surface supports
+    present_modes: Vec<wgpu::PresentMode>,
+
     surface_config: wgpu::SurfaceConfiguration,
     surface: wgpu::Surface,
 
@@ -59,42 +214,90 @@ pub(super) struct RenderState {
     window: Arc<Window>,
 }
 
-impl FromWorld for RenderState {
-    fn from_world(world: &mut World) -> Self {
-        let window = world
-            .get_non_send_resource::<InitWindowResource>()
-            .unwrap()
-            .main_window
-            .clone();
+impl RenderState {
+    fn new(window: Arc<Window>, settings: &RenderSettings) -> Result<Self, RenderError> {
+        let instance = wgpu::Instance::new(wgpu::InstanceDescriptor {
+            backends: settings.backends,
+            ..Default::default()
+        });
 
-        let instance = wgpu::Instance::new(wgpu::InstanceDescriptor::default());
+        let surface =
+            unsafe { instance.create_surface(&window) }.map_err(RenderError::CreateSurface)?;
 
-        let surface = unsafe { instance.create_surface(&window) }.unwrap();
+        // the adapter of the settings is tried first, then adapters with less capabilities until one of them works
+        let (adapter, device, queue, fallback_adapter) = pollster::block_on(async {
+            let fallback_limits = if settings.limits == wgpu::Limits::default() {
+                wgpu::Limits::downlevel_defaults()
+            } else {
+                settings.limits.clone()
+            };
+            let attempts = [
+                (
+                    false,
+                    settings.power_preference,
+                    settings.force_fallback_adapter,
+                    settings.limits.clone(),
+                ),
+                (
+                    true,
+                    wgpu::PowerPreference::LowPower,
+                    settings.force_fallback_adapter,
+                    fallback_limits.clone(),
+                ),
+                (true, wgpu::PowerPreference::LowPower, true, fallback_limits),
+            ];
 
-        let (adapter, device, queue) = pollster::block_on(async {
-            let adapter = instance
-                .request_adapter(&wgpu::RequestAdapterOptions {
-                    power_preference: wgpu::PowerPreference::HighPerformance,
-                    compatible_surface: Some(&surface),
-                    force_fallback_adapter: false,
-                })
-                .await
-                .unwrap();
+            let mut last_error = RenderError::NoAdapter;
+            for (fallback_adapter, power_preference, force_fallback_adapter, limits) in attempts {
+                let adapter = match &settings.adapter_name {
+                    // a named adapter has no fallback
+                    Some(_) if fallback_adapter => break,
+                    Some(name) => instance
+                        .enumerate_adapters(settings.backends)
+                        .find(|adapter| {
+                            adapter.get_info().name.contains(name.as_str())
+                                && adapter.is_surface_supported(&surface)
+                        }),
+                    None => {
+                        instance
+                            .request_adapter(&wgpu::RequestAdapterOptions {
+                                power_preference,
+                                compatible_surface: Some(&surface),
+                                force_fallback_adapter,
+                            })
+                            .await
+                    }
+                };
+                let Some(adapter) = adapter else {
+                    continue;
+                };
 
-            let (device, queue) = adapter
-                .request_device(
-                    &wgpu::DeviceDescriptor {
-                        features: wgpu::Features::empty(),
-                        limits: wgpu::Limits::default(),
-                        label: None,
-                    },
-                    None,
-                )
-                .await
-                .unwrap();
+                match adapter
+                    .request_device(
+                        &wgpu::DeviceDescriptor {
+                            // timestamp queries are only used for diagnostics, so they are requested when available
+                            features: settings.features
+                                | (adapter.features() & wgpu::Features::TIMESTAMP_QUERY),
+                            limits,
+                            label: None,
+                        },
+                        None,
+                    )
+                    .await
+                {
+                    Ok((device, queue)) => return Ok((adapter, device, queue, fallback_adapter)),
+                    Err(error) => last_error = RenderError::RequestDevice(error),
+                }
+            }
+            Err(last_error)
+        })?;
 
-            (adapter, device, queue)
-        });
+        // errors are collected instead of panicking, so `render` can report them and create the renderer again when the device was lost
+        let errors = Arc::new(Mutex::new(vec![]));
+        device.on_uncaptured_error(Box::new({
+            let errors = errors.clone();
+            move |error| errors.lock().unwrap().push(RenderError::from_wgpu(error))
+        }));
 
         let size = window.inner_size();
         let surface_capabilities = surface.get_capabilities(&adapter);
@@ -108,10 +311,13 @@ impl FromWorld for RenderState {
                 })
                 .max_by_key(|format| format.is_srgb())
                 .copied()
-                .expect("surface should support some kind of rgba8unorm format"),
+                .ok_or(RenderError::UnsupportedSurface)?,
             width: size.width.max(1),
             height: size.height.max(1),
-            present_mode: wgpu::PresentMode::AutoNoVsync,
+            present_mode: supported_present_mode(
+                settings.present_mode,
+                &surface_capabilities.present_modes,
+            ),
             alpha_mode: surface_capabilities
                 .alpha_modes
                 .iter()
@@ -122,47 +328,98 @@ impl FromWorld for RenderState {
         };
         surface.configure(&device, &surface_config);
 
-        let main_texture = device.create_texture(&wgpu::TextureDescriptor {
-            label: Some("Main Texture"),
-            size: wgpu::Extent3d {
-                width: surface_config.width,
-                height: surface_config.height,
-                depth_or_array_layers: 1,
-            },
-            mip_level_count: 1,
-            sample_count: 1,
-            dimension: wgpu::TextureDimension::D2,
-            format: wgpu::TextureFormat::Rgba8Unorm,
-            usage: wgpu::TextureUsages::STORAGE_BINDING | wgpu::TextureUsages::COPY_SRC,
-            view_formats: &[],
-        });
+        let post_process_bind_group_layout =
+            device.create_bind_group_layout(&wgpu::BindGroupLayoutDescriptor {
+                label: Some("Post Process Settings Bind Group Layout"),
+                entries: &[
+                    wgpu::BindGroupLayoutEntry {
+                        binding: 0,
+                        visibility: wgpu::ShaderStages::COMPUTE,
+                        ty: wgpu::BindingType::Buffer {
+                            ty: wgpu::BufferBindingType::Uniform,
+                            has_dynamic_offset: false,
+                            min_binding_size: Some(GpuPostProcess::SHADER_SIZE),
+                        },
+                        count: None,
+                    },
+                    wgpu::BindGroupLayoutEntry {
+                        binding: 1,
+                        visibility: wgpu::ShaderStages::COMPUTE,
+                        ty: wgpu::BindingType::Texture {
+                            sample_type: wgpu::TextureSampleType::Float { filterable: false },
+                            view_dimension: wgpu::TextureViewDimension::D3,
+                            multisampled: false,
+                        },
+                        count: None,
+                    },
+                ],
+            });
+
+        let post_process_pipelines =
+            PostProcessPipelines::new(&device, &post_process_bind_group_layout);
+        let output_bind_group_layout =
+            device.create_bind_group_layout(&wgpu::BindGroupLayoutDescriptor {
+                label: Some("Ray Tracing Output Bind Group Layout"),
+                entries: &[
+                    wgpu::BindGroupLayoutEntry {
+                        binding: 0,
+                        visibility: wgpu::ShaderStages::COMPUTE,
+                        ty: wgpu::BindingType::StorageTexture {
+                            access: wgpu::StorageTextureAccess::WriteOnly,
+                            format: HDR_TEXTURE_FORMAT,
+                            view_dimension: wgpu::TextureViewDimension::D2,
+                        },
+                        count: None,
+                    },
+                    wgpu::BindGroupLayoutEntry {
+                        binding: 1,
+                        visibility: wgpu::ShaderStages::COMPUTE,
+                        ty: wgpu::BindingType::StorageTexture {
+                            access: wgpu::StorageTextureAccess::WriteOnly,
+                            format: wgpu::TextureFormat::R32Float,
+                            view_dimension: wgpu::TextureViewDimension::D2,
+                        },
+                        count: None,
+                    },
+                    // the ray counters, the shader only uses them while rays are counted
+                    wgpu::BindGroupLayoutEntry {
+                        binding: 2,
+                        visibility: wgpu::ShaderStages::COMPUTE,
+                        ty: wgpu::BindingType::Buffer {
+                            ty: wgpu::BufferBindingType::Storage { read_only: false },
+                            has_dynamic_offset: false,
+                            min_binding_size: None,
+                        },
+                        count: None,
+                    },
+                    wgpu::BindGroupLayoutEntry {
+                        binding: 3,
+                        visibility: wgpu::ShaderStages::COMPUTE,
+                        ty: wgpu::BindingType::Buffer {
+                            ty: wgpu::BufferBindingType::Uniform,
+                            has_dynamic_offset: false,
+                            min_binding_size: Some(GpuRenderTile::SHADER_SIZE),
+                        },
+                        count: None,
+                    },
+                ],
+            });
 
-        let main_texture_bind_group_layout =
+        let depth_texture_bind_group_layout =
             device.create_bind_group_layout(&wgpu::BindGroupLayoutDescriptor {
-                label: Some("Main Texture Bind Group Layout"),
+                label: Some("Depth Texture Bind Group Layout"),
                 entries: &[wgpu::BindGroupLayoutEntry {
                     binding: 0,
-                    visibility: wgpu::ShaderStages::COMPUTE,
-                    ty: wgpu::BindingType::StorageTexture {
-                        access: wgpu::StorageTextureAccess::WriteOnly,
-                        format: wgpu::TextureFormat::Rgba8Unorm,
+                    visibility: wgpu::ShaderStages::VERTEX | wgpu::ShaderStages::FRAGMENT,
+                    ty: wgpu::BindingType::Texture {
+                        sample_type: wgpu::TextureSampleType::Float { filterable: false },
                         view_dimension: wgpu::TextureViewDimension::D2,
+                        multisampled: false,
                     },
                     count: None,
                 }],
             });
 
-        let main_texture_bind_group = device.create_bind_group(&wgpu::BindGroupDescriptor {
-            label: Some("Main Texture Bind Group"),
-            layout: &main_texture_bind_group_layout,
-            entries: &[wgpu::BindGroupEntry {
-                binding: 0,
-                resource: wgpu::BindingResource::TextureView(
-                    &main_texture.create_view(&wgpu::TextureViewDescriptor::default()),
-                ),
-            }],
-        });
-
         let camera_uniform_buffer = device.create_buffer(&wgpu::BufferDescriptor {
             label: Some("Camera Uniform Buffer"),
             size: GpuCamera::SHADER_SIZE.get(),
@@ -175,7 +432,7 @@ impl FromWorld for RenderState {
                 label: Some("Camera Bind Group Layout"),
                 entries: &[wgpu::BindGroupLayoutEntry {
                     binding: 0,
-                    visibility: wgpu::ShaderStages::COMPUTE,
+                    visibility: wgpu::ShaderStages::COMPUTE | wgpu::ShaderStages::VERTEX,
                     ty: wgpu::BindingType::Buffer {
                         ty: wgpu::BufferBindingType::Uniform,
                         has_dynamic_offset: false,
@@ -194,289 +451,2179 @@ impl FromWorld for RenderState {
             }],
         });
 
-        let sphere_bind_group_layout =
+        let mut font_atlas_data = vec![0; (FONT_ATLAS_WIDTH * FONT_ATLAS_HEIGHT) as usize];
+        for (character, glyph) in (0..).zip(font8x8::legacy::BASIC_LEGACY) {
+            for (row, bits) in (0..).zip(glyph) {
+                for column in 0..FONT_GLYPH_SIZE {
+                    if bits >> column & 1 != 0 {
+                        let x = character % FONT_ATLAS_COLUMNS * FONT_GLYPH_SIZE + column;
+                        let y = character / FONT_ATLAS_COLUMNS * FONT_GLYPH_SIZE + row;
+                        font_atlas_data[(y * FONT_ATLAS_WIDTH + x) as usize] = u8::MAX;
+                    }
+                }
+            }
+        }
+
+        let font_atlas_size = wgpu::Extent3d {
+            width: FONT_ATLAS_WIDTH,
+            height: FONT_ATLAS_HEIGHT,
+            depth_or_array_layers: 1,
+        };
+        let font_atlas = device.create_texture(&wgpu::TextureDescriptor {
+            label: Some("Font Atlas"),
+            size: font_atlas_size,
+            mip_level_count: 1,
+            sample_count: 1,
+            dimension: wgpu::TextureDimension::D2,
+            format: wgpu::TextureFormat::R8Unorm,
+            usage: wgpu::TextureUsages::TEXTURE_BINDING | wgpu::TextureUsages::COPY_DST,
+            view_formats: &[],
+        });
+        queue.write_texture(
+            font_atlas.as_image_copy(),
+            &font_atlas_data,
+            wgpu::ImageDataLayout {
+                offset: 0,
+                bytes_per_row: Some(FONT_ATLAS_WIDTH),
+                rows_per_image: None,
+            },
+            font_atlas_size,
+        );
+
+        let font_atlas_bind_group_layout =
+            device.create_bind_group_layout(&wgpu::BindGroupLayoutDescriptor {
+                label: Some("Font Atlas Bind Group Layout"),
+                entries: &[wgpu::BindGroupLayoutEntry {
+                    binding: 0,
+                    visibility: wgpu::ShaderStages::FRAGMENT,
+                    ty: wgpu::BindingType::Texture {
+                        sample_type: wgpu::TextureSampleType::Float { filterable: false },
+                        view_dimension: wgpu::TextureViewDimension::D2,
+                        multisampled: false,
+                    },
+                    count: None,
+                }],
+            });
+
+        let font_atlas_bind_group = device.create_bind_group(&wgpu::BindGroupDescriptor {
+            label: Some("Font Atlas Bind Group"),
+            layout: &font_atlas_bind_group_layout,
+            entries: &[wgpu::BindGroupEntry {
+                binding: 0,
+                resource: wgpu::BindingResource::TextureView(
+                    &font_atlas.create_view(&wgpu::TextureViewDescriptor::default()),
+                ),
+            }],
+        });
+
+        let overlay_bind_group_layout =
             device.create_bind_group_layout(&wgpu::BindGroupLayoutDescriptor {
-                label: Some("Sphere Bind Group Layout"),
+                label: Some("Overlay Bind Group Layout"),
                 entries: &[wgpu::BindGroupLayoutEntry {
                     binding: 0,
-                    visibility: wgpu::ShaderStages::COMPUTE,
+                    visibility: wgpu::ShaderStages::VERTEX,
                     ty: wgpu::BindingType::Buffer {
                         ty: wgpu::BufferBindingType::Storage { read_only: true },
                         has_dynamic_offset: false,
-                        min_binding_size: Some(GpuSpheres::<'_>::min_size()),
+                        min_binding_size: Some(GpuOverlayQuads::<'_>::min_size()),
                     },
                     count: None,
                 }],
             });
 
-        let ray_tracing_pipeline_layout =
+        let overlay_pipeline_layout =
             device.create_pipeline_layout(&wgpu::PipelineLayoutDescriptor {
-                label: Some("Ray Tracing Pipeline Layout"),
-                bind_group_layouts: &[
-                    &main_texture_bind_group_layout,
-                    &camera_bind_group_layout,
-                    &sphere_bind_group_layout,
-                ],
+                label: Some("Overlay Pipeline Layout"),
+                bind_group_layouts: &[&font_atlas_bind_group_layout, &overlay_bind_group_layout],
                 push_constant_ranges: &[],
             });
 
-        let ray_tracing_shader =
-            device.create_shader_module(wgpu::include_wgsl!("./ray_tracing.wgsl"));
-        let ray_tracing_pipeline =
-            device.create_compute_pipeline(&wgpu::ComputePipelineDescriptor {
-                label: Some("Ray Tracing Pipeline"),
-                layout: Some(&ray_tracing_pipeline_layout),
-                module: &ray_tracing_shader,
-                entry_point: "ray_trace",
-            });
+        let overlay_shader = device.create_shader_module(wgpu::include_wgsl!("./overlay.wgsl"));
+        let overlay_pipeline = device.create_render_pipeline(&wgpu::RenderPipelineDescriptor {
+            label: Some("Overlay Pipeline"),
+            layout: Some(&overlay_pipeline_layout),
+            vertex: wgpu::VertexState {
+                module: &overlay_shader,
+                entry_point: "vertex",
+                buffers: &[],
+            },
+            primitive: wgpu::PrimitiveState::default(),
+            depth_stencil: None,
+            multisample: wgpu::MultisampleState::default(),
+            fragment: Some(wgpu::FragmentState {
+                module: &overlay_shader,
+                entry_point: "fragment",
+                targets: &[Some(wgpu::ColorTargetState {
+                    format: MAIN_TEXTURE_FORMAT,
+                    blend: None,
+                    write_mask: wgpu::ColorWrites::ALL,
+                })],
+            }),
+            multiview: None,
+        });
 
-        RenderState {
-            ray_tracing_pipeline,
+        let gpu_timer = GpuTimer::new(&device, &queue);
+        let ray_counters = RayCounters::new(&device);
+
+        Ok(RenderState {
+            overlay_pipeline,
 
-            sphere_bind_group_layout,
+            overlay_bind_group_layout,
+            post_process_bind_group_layout,
+
+            post_process_pipelines,
+
+            font_atlas_bind_group,
 
             camera_bind_group,
+            camera_bind_group_layout,
             camera_uniform_buffer,
 
-            main_texture_bind_group,
-            main_texture_bind_group_layout,
-            main_texture,
+            output_bind_group_layout,
+            depth_texture_bind_group_layout,
 
             queue,
             device,
 
+            gpu_timer,
+            ray_counters,
+            frames: 0,
+            ray_tracing_time: None,
+            uploaded_bytes: AtomicU64::new(0),
+            surface_configurations: 0,
+            errors,
+            adapter_info: adapter.get_info(),
+            present_modes: surface_capabilities.present_modes,
+            fallback_adapter,
+
             surface_config,
             surface,
 
             window,
-        }
+        })
     }
 }
 
-impl RenderState {
-    fn resize(&mut self, width: u32, height: u32) {
-        self.surface_config.width = width.max(1);
-        self.surface_config.height = height.max(1);
-        self.surface.configure(&self.device, &self.surface_config);
-
-        self.main_texture = self.device.create_texture(&wgpu::TextureDescriptor {
-            label: Some("Main Texture"),
-            size: wgpu::Extent3d {
-                width: self.surface_config.width,
-                height: self.surface_config.height,
-                depth_or_array_layers: 1,
-            },
-            mip_level_count: 1,
-            sample_count: 1,
-            dimension: wgpu::TextureDimension::D2,
-            format: wgpu::TextureFormat::Rgba8Unorm,
-            usage: wgpu::TextureUsages::STORAGE_BINDING | wgpu::TextureUsages::COPY_SRC,
-            view_formats: &[],
-        });
+// how long to wait before creating the renderer again after it failed
+const RETRY_INTERVAL: Duration = Duration::from_secs(1);
 
-        self.main_texture_bind_group = self.device.create_bind_group(&wgpu::BindGroupDescriptor {
-            label: Some("Main Texture Bind Group"),
-            layout: &self.main_texture_bind_group_layout,
-            entries: &[wgpu::BindGroupEntry {
-                binding: 0,
-                resource: wgpu::BindingResource::TextureView(
-                    &self
-                        .main_texture
-                        .create_view(&wgpu::TextureViewDescriptor::default()),
-                ),
-            }],
-        });
-    }
-}
+// registers a primitive type with a new `PrimitiveState`
+type RegisterPrimitive = fn(&mut PrimitiveState, &World) -> bool;
 
+// what is needed to create the renderer again after it failed
 #[derive(Resource)]
-pub(super) struct SphereState {
-    sphere_buffer: wgpu::Buffer,
-    sphere_bind_group: wgpu::BindGroup,
-    spheres: Vec<GpuSphere>,
-    buffer: Vec<u8>,
+pub(super) struct RenderRecovery {
+    window: Arc<Window>,
+    // the registered primitive types, they are registered with every new `PrimitiveState`
+    primitives: Vec<(TypeId, RegisterPrimitive)>,
+    last_attempt: Option<Instant>,
 }
 
-impl FromWorld for SphereState {
+impl FromWorld for RenderRecovery {
     fn from_world(world: &mut World) -> Self {
-        let render_state = world.get_resource_mut::<RenderState>().unwrap();
+        let window = world
+            .get_non_send_resource::<InitWindowResource>()
+            .unwrap()
+            .main_window
+            .clone();
+        Self {
+            window,
+            primitives: vec![],
+            last_attempt: None,
+        }
+    }
+}
 
-        let sphere_buffer = render_state.device.create_buffer(&wgpu::BufferDescriptor {
-            label: Some("Sphere Buffer"),
-            size: GpuSpheres::<'_>::min_size().get(),
-            usage: wgpu::BufferUsages::COPY_DST | wgpu::BufferUsages::STORAGE,
-            mapped_at_creation: false,
-        });
+impl RenderRecovery {
+    /// returns false if the type was already registered
+    pub(super) fn register_primitive<T: Primitive>(&mut self) -> bool {
+        if self
+            .primitives
+            .iter()
+            .any(|&(type_id, _)| type_id == TypeId::of::<T>())
+        {
+            return false;
+        }
+        self.primitives
+            .push((TypeId::of::<T>(), |primitive_state, world| {
+                primitive_state.register::<T>(world)
+            }));
+        true
+    }
+}
 
-        let sphere_bind_group = render_state
-            .device
-            .create_bind_group(&wgpu::BindGroupDescriptor {
-                label: Some("Sphere Bind Group"),
-                layout: &render_state.sphere_bind_group_layout,
-                entries: &[wgpu::BindGroupEntry {
-                    binding: 0,
-                    resource: sphere_buffer.as_entire_binding(),
-                }],
-            });
+/// creates the renderer and every resource that holds gpu objects, replacing the ones of a previous renderer
+pub(super) fn create_renderer(world: &mut World) {
+    let mut recovery = world.resource_mut::<RenderRecovery>();
+    recovery.last_attempt = Some(Instant::now());
 
-        SphereState {
-            sphere_buffer,
-            sphere_bind_group,
-            spheres: vec![],
-            buffer: vec![],
+    let window = recovery.window.clone();
+    let render_state = match validate_shaders()
+        .and_then(|()| RenderState::new(window, world.resource::<RenderSettings>()))
+    {
+        Ok(render_state) => render_state,
+        Err(error) => {
+            report_error(world, error);
+            return;
+        }
+    };
+    *world.resource_mut::<RenderStatus>() = RenderStatus::Running {
+        adapter: render_state.adapter_info.clone(),
+        fallback_adapter: render_state.fallback_adapter,
+    };
+    world.insert_resource(render_state);
+
+    let mut primitive_state = match PrimitiveState::new(world) {
+        Ok(primitive_state) => primitive_state,
+        Err(error) => {
+            destroy_renderer(world);
+            report_error(world, error);
+            return;
         }
+    };
+    for (_, register) in world.resource::<RenderRecovery>().primitives.clone() {
+        register(&mut primitive_state, world);
     }
-}
+    world.insert_resource(primitive_state);
+    let gizmo_state = GizmoState::from_world(world);
+    world.insert_resource(gizmo_state);
+    let overlay_state = OverlayState::from_world(world);
+    world.insert_resource(overlay_state);
+    let post_process_state = PostProcessState::from_world(world);
+    world.insert_resource(post_process_state);
 
-pub(super) fn update_spheres(
-    render_state: Res<RenderState>,
-    mut sphere_state: ResMut<SphereState>,
-    spheres: Query<(Ref<GlobalTransform>, Ref<Material>, Ref<Sphere>)>,
-) {
-    let sphere_state: &mut SphereState = &mut sphere_state;
-
-    let previous_sphere_count = sphere_state.spheres.len();
-    sphere_state.buffer.clear();
-
-    let mut components_changed = false;
-    sphere_state.spheres.clear();
-    spheres.for_each(|(transform, material, sphere)| {
-        components_changed |=
-            transform.is_changed() || material.is_changed() || sphere.is_changed();
-        let Material { color } = *material;
-        let Sphere { radius } = *sphere;
-        sphere_state.spheres.push(GpuSphere {
-            transform: transform.transform().motor,
-            color,
-            radius,
-        });
-    });
+    world.resource_mut::<RenderGraph>().reset();
+    world.resource_mut::<Materials>().mark_all_changed();
+}
 
-    if components_changed || sphere_state.spheres.len() != previous_sphere_count {
-        let mut buffer = StorageBuffer::new(&mut sphere_state.buffer);
-        buffer
-            .write(&GpuSpheres {
-                length: ArrayLength,
-                data: &sphere_state.spheres,
-            })
-            .unwrap();
+// drops every resource that holds gpu objects, `recreate_renderer` creates them again
+fn destroy_renderer(world: &mut World) {
+    world.remove_resource::<PostProcessState>();
+    world.remove_resource::<OverlayState>();
+    world.remove_resource::<GizmoState>();
+    world.remove_resource::<PrimitiveState>();
+    world.remove_resource::<RenderState>();
+    *world.resource_mut::<RenderStatus>() = RenderStatus::Unavailable;
+}
 
-        if sphere_state.buffer.len() as wgpu::BufferAddress > sphere_state.sphere_buffer.size() {
-            sphere_state.sphere_buffer =
-                render_state.device.create_buffer(&wgpu::BufferDescriptor {
-                    label: Some("Sphere Buffer"),
-                    size: sphere_state.buffer.len() as wgpu::BufferAddress,
-                    usage: wgpu::BufferUsages::COPY_DST | wgpu::BufferUsages::STORAGE,
-                    mapped_at_creation: false,
-                });
+fn report_error(world: &mut World, error: RenderError) {
+    error!("{error}");
+    world.send_event(error);
+}
 
-            sphere_state.sphere_bind_group =
-                render_state
-                    .device
-                    .create_bind_group(&wgpu::BindGroupDescriptor {
-                        label: Some("Sphere Bind Group"),
-                        layout: &render_state.sphere_bind_group_layout,
-                        entries: &[wgpu::BindGroupEntry {
-                            binding: 0,
-                            resource: sphere_state.sphere_buffer.as_entire_binding(),
-                        }],
-                    });
-        }
+pub(super) fn recreate_renderer(world: &mut World) {
+    if world.contains_resource::<RenderState>() {
+        return;
+    }
+    let retry = match world.resource::<RenderRecovery>().last_attempt {
+        Some(last_attempt) => last_attempt.elapsed() >= RETRY_INTERVAL,
+        None => true,
+    };
+    if retry {
+        create_renderer(world);
+    }
+}
 
-        render_state
-            .queue
-            .write_buffer(&sphere_state.sphere_buffer, 0, &sphere_state.buffer);
+// `present_mode` if the surface supports it, otherwise the automatic mode with the same vsync
+fn supported_present_mode(
+    present_mode: wgpu::PresentMode,
+    present_modes: &[wgpu::PresentMode],
+) -> wgpu::PresentMode {
+    match present_mode {
+        wgpu::PresentMode::AutoVsync | wgpu::PresentMode::AutoNoVsync => present_mode,
+        _ if present_modes.contains(&present_mode) => present_mode,
+        wgpu::PresentMode::Fifo | wgpu::PresentMode::FifoRelaxed => wgpu::PresentMode::AutoVsync,
+        _ => wgpu::PresentMode::AutoNoVsync,
     }
 }
 
-pub(super) fn update_camera(
-    render_state: Res<RenderState>,
-    camera: Query<(Ref<GlobalTransform>, Ref<Camera>, Ref<MainCamera>)>,
+pub(super) fn update_present_mode(
+    mut render_state: ResMut<RenderState>,
+    settings: Res<RenderSettings>,
 ) {
-    let (global_transform, camera, main_camera) = camera.single();
-    if global_transform.is_changed() || camera.is_changed() || main_camera.is_changed() {
-        let mut buffer = UniformBuffer::new([0; GpuCamera::SHADER_SIZE.get() as _]);
-        let Camera {
-            v_fov,
-            min_distance,
-            max_distance,
-            sun_direction,
-        } = *camera;
-        buffer
-            .write(&GpuCamera {
-                transform: global_transform.transform().motor,
-                v_fov,
-                min_distance,
-                max_distance,
-                sun_direction,
-            })
-            .unwrap();
-        render_state.queue.write_buffer(
-            &render_state.camera_uniform_buffer,
-            0,
-            &buffer.into_inner(),
-        );
+    if !settings.is_changed() {
+        return;
+    }
+    let present_mode = supported_present_mode(settings.present_mode, &render_state.present_modes);
+    if render_state.surface_config.present_mode != present_mode {
+        render_state.surface_config.present_mode = present_mode;
+        render_state.configure_surface();
     }
 }
 
-pub(super) fn render(mut render_state: ResMut<RenderState>, sphere_state: Res<SphereState>) {
-    let output = loop {
-        match render_state.surface.get_current_texture() {
-            Ok(output) => break output,
-            Err(error) => match error {
-                e @ wgpu::SurfaceError::Timeout => {
-                    eprintln!("{e}");
-                    return;
-                }
-
-                wgpu::SurfaceError::Outdated => {
-                    let size = render_state.window.inner_size();
-                    render_state.resize(size.width, size.height);
-                }
-
-                wgpu::SurfaceError::Lost => {
-                    render_state
-                        .surface
-                        .configure(&render_state.device, &render_state.surface_config);
-                }
+// the render scale changes in steps of this, so the scaled textures are not created again every frame
+const RENDER_SCALE_STEP: f32 = 0.05;
+// how far an adaptive scale moves each frame toward the scale that would take the target frame time
+const ADAPTIVE_RENDER_SCALE_RATE: f32 = 0.05;
 
-                e @ wgpu::SurfaceError::OutOfMemory => panic!("{e}"),
-            },
+pub(super) fn update_render_scale(
+    mut render_graph: ResMut<RenderGraph>,
+    render_scale: Res<RenderScale>,
+    diagnostics: Res<DiagnosticsStore>,
+    // the adaptive scale before it is rounded to a step
+    mut adaptive_scale: Local<Option<f32>>,
+) {
+    let scale = match *render_scale {
+        RenderScale::Fixed(scale) => {
+            *adaptive_scale = None;
+            scale
+        }
+        RenderScale::Adaptive {
+            target_frame_time,
+            min_scale,
+            max_scale,
+        } => {
+            let scale =
+                adaptive_scale.get_or_insert_with(|| render_graph.resources().render_scale());
+            let frame_time = diagnostics
+                .get(FrameTimeDiagnosticsPlugin::FRAME_TIME)
+                .and_then(|diagnostic| diagnostic.smoothed())
+                .filter(|&frame_time| frame_time > 0.0);
+            if let Some(frame_time) = frame_time {
+                // ray tracing takes about as long as there are pixels, which grow with the square of the scale
+                let target_scale = *scale * (target_frame_time / frame_time as f32).sqrt();
+                *scale += (target_scale - *scale) * ADAPTIVE_RENDER_SCALE_RATE;
+            }
+            *scale = scale.max(min_scale).min(max_scale);
+            ((*scale / RENDER_SCALE_STEP).round() * RENDER_SCALE_STEP)
+                .max(min_scale)
+                .min(max_scale)
         }
     };
+    if render_graph.resources().render_scale() != scale {
+        render_graph.set_render_scale(scale);
+    }
+}
 
-    let mut encoder = render_state
-        .device
-        .create_command_encoder(&wgpu::CommandEncoderDescriptor {
-            label: Some("Render Encoder"),
-        });
-    {
-        let mut ray_tracing_pass = encoder.begin_compute_pass(&wgpu::ComputePassDescriptor {
-            label: Some("Ray Tracing Pass"),
-            timestamp_writes: None,
-        });
+impl RenderState {
+    fn resize(&mut self, width: u32, height: u32) {
+        self.surface_config.width = width.max(1);
+        self.surface_config.height = height.max(1);
+        self.configure_surface();
+    }
 
-        ray_tracing_pass.set_pipeline(&render_state.ray_tracing_pipeline);
-        ray_tracing_pass.set_bind_group(0, &render_state.main_texture_bind_group, &[]);
-        ray_tracing_pass.set_bind_group(1, &render_state.camera_bind_group, &[]);
-        ray_tracing_pass.set_bind_group(2, &sphere_state.sphere_bind_group, &[]);
-        ray_tracing_pass.dispatch_workgroups(
-            (render_state.main_texture.width() + (16 - 1)) / 16,
-            (render_state.main_texture.height() + (16 - 1)) / 16,
-            1,
-        );
+    fn configure_surface(&mut self) {
+        self.surface.configure(&self.device, &self.surface_config);
+        self.surface_configurations += 1;
     }
-    encoder.copy_texture_to_texture(
-        render_state.main_texture.as_image_copy(),
-        output.texture.as_image_copy(),
-        wgpu::Extent3d {
-            width: render_state.surface_config.width,
-            height: render_state.surface_config.height,
-            depth_or_array_layers: 1,
-        },
-    );
-    render_state.queue.submit([encoder.finish()]);
 
-    render_state.window.pre_present_notify();
-    output.present();
+    fn count_upload(&self, bytes: u64) {
+        self.uploaded_bytes.fetch_add(bytes, Ordering::Relaxed);
+    }
+
+    // the textures the ray tracer writes to, and the part of the image they hold
+    fn create_output_bind_group(
+        &self,
+        hdr_texture: &wgpu::Texture,
+        depth_texture: &wgpu::Texture,
+        tile_buffer: &wgpu::Buffer,
+    ) -> wgpu::BindGroup {
+        self.device.create_bind_group(&wgpu::BindGroupDescriptor {
+            label: Some("Ray Tracing Output Bind Group"),
+            layout: &self.output_bind_group_layout,
+            entries: &[
+                wgpu::BindGroupEntry {
+                    binding: 0,
+                    resource: wgpu::BindingResource::TextureView(
+                        &hdr_texture.create_view(&wgpu::TextureViewDescriptor::default()),
+                    ),
+                },
+                wgpu::BindGroupEntry {
+                    binding: 1,
+                    resource: wgpu::BindingResource::TextureView(
+                        &depth_texture.create_view(&wgpu::TextureViewDescriptor::default()),
+                    ),
+                },
+                wgpu::BindGroupEntry {
+                    binding: 2,
+                    resource: self.ray_counters.buffer().as_entire_binding(),
+                },
+                wgpu::BindGroupEntry {
+                    binding: 3,
+                    resource: tile_buffer.as_entire_binding(),
+                },
+            ],
+        })
+    }
+}
+
+fn render_tile_bytes(tile: GpuRenderTile) -> Vec<u8> {
+    let mut buffer = UniformBuffer::new(vec![]);
+    buffer.write(&tile).unwrap();
+    buffer.into_inner()
+}
+
+// the storage buffer of a single registered primitive type
+struct PrimitiveBuffer {
+    type_id: TypeId,
+    shader_type: &'static str,
+    shader_intersect: &'static str,
+    shader_intersect_by_index: bool,
+    shader: &'static str,
+    shader_file: Option<&'static str>,
+    layout: StructLayout,
+    min_binding_size: wgpu::BufferSize,
+    // a `GpuSlotBuffer<T::GpuData>`, and a function that gets its buffer
+    array: Box<dyn Any + Send + Sync>,
+    buffer: fn(&(dyn Any + Send + Sync)) -> &wgpu::Buffer,
+    // the number of entities with this primitive
+    count: usize,
+}
+
+#[derive(Resource)]
+pub(super) struct PrimitiveState {
+    materials: GpuArrayBuffer<GpuMaterial>,
+    primitive_buffers: Vec<PrimitiveBuffer>,
+    bind_group_layout: wgpu::BindGroupLayout,
+    bind_group: wgpu::BindGroup,
+    ray_tracing_pipeline: wgpu::ComputePipeline,
+    // set when a primitive type was registered, the pipeline and bind group layout have to be recreated
+    pipeline_changed: bool,
+    // set when a primitive buffer was recreated, the bind group has to be recreated
+    buffers_changed: bool,
+    // how many primitive types the bind group layout and pipeline were created for,
+    // types that were registered while the shader failed to compile are not bound until it compiles
+    pipeline_primitive_count: usize,
+    // the pipeline only counts intersection tests while they are shown, because it makes every intersection slower
+    count_intersection_tests: bool,
+    // whether the pipeline counts rays for the ray statistics
+    count_rays: bool,
+    // the files that were read by hot reloading, by default the compiled in shaders are used
+    shader_files: ShaderFiles,
+}
+
+impl PrimitiveState {
+    fn new(world: &mut World) -> Result<Self, RenderError> {
+        let render_state = world.get_resource_mut::<RenderState>().unwrap();
+        let materials = GpuArrayBuffer::new(
+            &render_state.device,
+            "Material Buffer",
+            wgpu::ShaderStages::COMPUTE,
+        );
+        let (bind_group_layout, ray_tracing_pipeline) =
+            PrimitiveState::create_pipeline(&render_state, &[], &[], &ShaderFiles::default())?;
+        let bind_group =
+            PrimitiveState::create_bind_group(&render_state, &bind_group_layout, &materials, &[]);
+
+        Ok(PrimitiveState {
+            materials,
+            primitive_buffers: vec![],
+            bind_group_layout,
+            bind_group,
+            ray_tracing_pipeline,
+            pipeline_changed: false,
+            buffers_changed: false,
+            pipeline_primitive_count: 0,
+            count_intersection_tests: false,
+            count_rays: false,
+            shader_files: ShaderFiles::default(),
+        })
+    }
+
+    /// returns false if the type was already registered
+    pub(super) fn register<T: Primitive>(&mut self, world: &World) -> bool {
+        if self
+            .primitive_buffers
+            .iter()
+            .any(|primitive_buffer| primitive_buffer.type_id == TypeId::of::<T>())
+        {
+            return false;
+        }
+
+        let render_state = world.resource::<RenderState>();
+        self.primitive_buffers.push(PrimitiveBuffer {
+            type_id: TypeId::of::<T>(),
+            shader_type: T::SHADER_TYPE,
+            shader_intersect: T::SHADER_INTERSECT,
+            shader_intersect_by_index: T::SHADER_INTERSECT_BY_INDEX,
+            shader: T::SHADER,
+            shader_file: T::SHADER_FILE,
+            layout: T::GpuData::struct_layout(),
+            min_binding_size: GpuSlotBuffer::<T::GpuData>::min_binding_size(),
+            array: Box::new(GpuSlotBuffer::<T::GpuData>::new(
+                &render_state.device,
+                T::SHADER_TYPE,
+                wgpu::ShaderStages::COMPUTE,
+            )),
+            buffer: |array| {
+                array
+                    .downcast_ref::<GpuSlotBuffer<T::GpuData>>()
+                    .unwrap()
+                    .buffer()
+            },
+            count: 0,
+        });
+        self.pipeline_changed = true;
+        true
+    }
+
+    fn create_pipeline(
+        render_state: &RenderState,
+        primitive_buffers: &[PrimitiveBuffer],
+        shader_defs: &[&str],
+        shader_files: &ShaderFiles,
+    ) -> Result<(wgpu::BindGroupLayout, wgpu::ComputePipeline), RenderError> {
+        // the shader is checked first, so a broken shader can be reported instead of failing inside wgpu
+        let structs = [
+            ("Camera", GpuCamera::struct_layout()),
+            ("Material", GpuMaterial::struct_layout()),
+            ("RenderTile", GpuRenderTile::struct_layout()),
+        ]
+        .into_iter()
+        .chain(primitive_buffers.iter().map(|primitive_buffer| {
+            (
+                primitive_buffer.shader_type,
+                primitive_buffer.layout.clone(),
+            )
+        }))
+        .collect::<Vec<_>>();
+        let ray_tracing_source =
+            ray_tracing_shader_source(primitive_buffers, shader_defs, shader_files)
+                .map_err(|error| ShaderLayoutError::Invalid(error.to_string()))
+                .and_then(|source| {
+                    validate_struct_layouts(&source, &structs)?;
+                    Ok(source)
+                })
+                .map_err(|error| RenderError::ShaderLayout {
+                    shader: "ray_tracing.wgsl",
+                    error,
+                })?;
+
+        let bind_group_layout =
+            render_state
+                .device
+                .create_bind_group_layout(&wgpu::BindGroupLayoutDescriptor {
+                    label: Some("Primitive Bind Group Layout"),
+                    // the materials are at binding 0, followed by one buffer per primitive type
+                    entries: &std::iter::once(
+                        GpuArrayBuffer::<GpuMaterial>::bind_group_layout_entry(
+                            0,
+                            wgpu::ShaderStages::COMPUTE,
+                        ),
+                    )
+                    .chain(
+                        (1..)
+                            .zip(primitive_buffers)
+                            .map(|(binding, primitive_buffer)| wgpu::BindGroupLayoutEntry {
+                                binding,
+                                visibility: wgpu::ShaderStages::COMPUTE,
+                                ty: wgpu::BindingType::Buffer {
+                                    ty: wgpu::BufferBindingType::Storage { read_only: true },
+                                    has_dynamic_offset: false,
+                                    min_binding_size: Some(primitive_buffer.min_binding_size),
+                                },
+                                count: None,
+                            }),
+                    )
+                    .collect::<Vec<_>>(),
+                });
+
+        let ray_tracing_pipeline_layout =
+            render_state
+                .device
+                .create_pipeline_layout(&wgpu::PipelineLayoutDescriptor {
+                    label: Some("Ray Tracing Pipeline Layout"),
+                    bind_group_layouts: &[
+                        &render_state.output_bind_group_layout,
+                        &render_state.camera_bind_group_layout,
+                        &bind_group_layout,
+                    ],
+                    push_constant_ranges: &[],
+                });
+
+        let ray_tracing_shader =
+            render_state
+                .device
+                .create_shader_module(wgpu::ShaderModuleDescriptor {
+                    label: Some("ray_tracing.wgsl"),
+                    source: wgpu::ShaderSource::Wgsl(ray_tracing_source.into()),
+                });
+        let ray_tracing_pipeline =
+            render_state
+                .device
+                .create_compute_pipeline(&wgpu::ComputePipelineDescriptor {
+                    label: Some("Ray Tracing Pipeline"),
+                    layout: Some(&ray_tracing_pipeline_layout),
+                    module: &ray_tracing_shader,
+                    entry_point: "ray_trace",
+                });
+
+        Ok((bind_group_layout, ray_tracing_pipeline))
+    }
+
+    fn create_bind_group(
+        render_state: &RenderState,
+        bind_group_layout: &wgpu::BindGroupLayout,
+        materials: &GpuArrayBuffer<GpuMaterial>,
+        primitive_buffers: &[PrimitiveBuffer],
+    ) -> wgpu::BindGroup {
+        render_state
+            .device
+            .create_bind_group(&wgpu::BindGroupDescriptor {
+                label: Some("Primitive Bind Group"),
+                layout: bind_group_layout,
+                entries: &std::iter::once(wgpu::BindGroupEntry {
+                    binding: 0,
+                    resource: materials.buffer().as_entire_binding(),
+                })
+                .chain(
+                    (1..)
+                        .zip(primitive_buffers)
+                        .map(|(binding, primitive_buffer)| wgpu::BindGroupEntry {
+                            binding,
+                            resource: (primitive_buffer.buffer)(&*primitive_buffer.array)
+                                .as_entire_binding(),
+                        }),
+                )
+                .collect::<Vec<_>>(),
+            })
+    }
+}
+
+// appends the bindings of every primitive type to the ray tracing shader,
+// and the functions that intersect a ray with all of them, then resolves the imports of all of it
+fn ray_tracing_shader_source(
+    primitive_buffers: &[PrimitiveBuffer],
+    shader_defs: &[&str],
+    shader_files: &ShaderFiles,
+) -> Result<String, ShaderComposeError> {
+    let mut source = shader_files
+        .get("ray_tracing.wgsl", include_str!("./ray_tracing.wgsl"))
+        .to_string();
+    let mut intersect_primitives = String::new();
+    let mut primitive_count = String::from("0u");
+
+    // binding 0 holds the materials
+    for (binding, primitive_buffer) in (1..).zip(primitive_buffers) {
+        let PrimitiveBuffer {
+            shader_type,
+            shader_intersect,
+            shader_intersect_by_index,
+            shader,
+            shader_file,
+            ..
+        } = primitive_buffer;
+        let shader = match shader_file {
+            Some(shader_file) => shader_files.get(shader_file, shader),
+            None => shader,
+        };
+        let primitive = if *shader_intersect_by_index {
+            "index".to_string()
+        } else {
+            format!("primitives_{shader_type}.data[index].value")
+        };
+
+        source += &format!(
+            "
+{shader}
+
+struct {shader_type}Slot {{
+    alive: u32,
+    value: {shader_type},
+}}
+
+struct {shader_type}Slots {{
+    length: u32,
+    data: array<{shader_type}Slot>,
+}}
+
+@group(2)
+@binding({binding})
+var<storage, read> primitives_{shader_type}: {shader_type}Slots;
+"
+        );
+        intersect_primitives += &format!(
+            "
+    for (var index = 0u; index < primitives_{shader_type}.length; index += 1u) {{
+        if primitives_{shader_type}.data[index].alive == 0u {{
+            continue;
+        }}
+#ifdef COUNT_INTERSECTION_TESTS
+        intersection_tests += 1u;
+#endif
+        let hit = {shader_intersect}(ray, {primitive});
+        if hit.hit && (!closest_hit.hit || hit.distance < closest_hit.distance) {{
+            closest_hit = hit;
+        }}
+    }}
+"
+        );
+        primitive_count += &format!(" + primitives_{shader_type}.length");
+    }
+
+    source += &format!(
+        "
+fn intersect_primitives(ray: Ray) -> Hit {{
+    var closest_hit: Hit;
+    closest_hit.hit = false;
+{intersect_primitives}
+    return closest_hit;
+}}
+
+fn primitive_count() -> u32 {{
+    return {primitive_count};
+}}
+"
+    );
+
+    shader_files.compose(&source, shader_defs)
+}
+
+type PrimitiveQuery<'a, T> = (
+    Entity,
+    Ref<'a, GlobalTransform>,
+    Ref<'a, PreviousGlobalTransform>,
+    Ref<'a, MaterialHandle>,
+    Ref<'a, T>,
+);
+
+pub(super) fn update_primitives<T: Primitive>(
+    render_state: Res<RenderState>,
+    mut primitive_state: ResMut<PrimitiveState>,
+    primitives: Query<PrimitiveQuery<'_, T>>,
+) {
+    let _span = info_span!("update_primitives", primitive = T::SHADER_TYPE).entered();
+    let primitive_state: &mut PrimitiveState = &mut primitive_state;
+
+    let primitive_buffer = primitive_state
+        .primitive_buffers
+        .iter_mut()
+        .find(|primitive_buffer| primitive_buffer.type_id == TypeId::of::<T>())
+        .unwrap();
+    primitive_buffer.count = primitives.iter().len();
+    let array = primitive_buffer
+        .array
+        .downcast_mut::<GpuSlotBuffer<T::GpuData>>()
+        .unwrap();
+    array.update(
+        &render_state.device,
+        &render_state.queue,
+        primitives.iter().map(
+            |(entity, transform, previous_transform, material, primitive)| {
+                (
+                    entity,
+                    transform.is_changed()
+                        || previous_transform.is_changed()
+                        || material.is_changed()
+                        || primitive.is_changed(),
+                    move || primitive.gpu_data(&transform, &previous_transform, *material),
+                )
+            },
+        ),
+    );
+    render_state.count_upload(array.uploaded_bytes());
+    primitive_state.buffers_changed |= array.buffer_recreated();
+}
+
+pub(super) fn update_materials(
+    render_state: Res<RenderState>,
+    mut primitive_state: ResMut<PrimitiveState>,
+    mut materials: ResMut<Materials>,
+) {
+    if !materials.is_changed() {
+        return;
+    }
+
+    let primitive_state: &mut PrimitiveState = &mut primitive_state;
+    primitive_state.materials.set(
+        &render_state.device,
+        &render_state.queue,
+        materials
+            .bypass_change_detection()
+            .drain_changed()
+            .map(|(index, material)| {
+                (
+                    index as usize,
+                    GpuMaterial {
+                        color: material.color,
+                        emission: material.emission,
+                    },
+                )
+            }),
+    );
+    render_state.count_upload(primitive_state.materials.uploaded_bytes());
+    primitive_state.buffers_changed |= primitive_state.materials.buffer_recreated();
+}
+
+pub(super) fn hot_reload_shaders(
+    mut primitive_state: ResMut<PrimitiveState>,
+    hot_reload: Option<ResMut<ShaderHotReload>>,
+) {
+    let Some(mut hot_reload) = hot_reload else {
+        return;
+    };
+    if !hot_reload.files_changed() {
+        return;
+    }
+
+    match ShaderFiles::read(&hot_reload.directory) {
+        Ok(shader_files) => {
+            primitive_state.shader_files = shader_files;
+            primitive_state.pipeline_changed = true;
+        }
+        Err(error) => error!(
+            "failed to read the shaders in {}: {error}",
+            hot_reload.directory.display()
+        ),
+    }
+}
+
+pub(super) fn prepare_primitives(
+    render_state: Res<RenderState>,
+    mut primitive_state: ResMut<PrimitiveState>,
+    debug_view: Res<DebugView>,
+    count_rays: Option<Res<CountRays>>,
+) {
+    let primitive_state: &mut PrimitiveState = &mut primitive_state;
+
+    let count_rays = count_rays.is_some();
+    // the ray statistics include the intersection tests
+    let count_intersection_tests = *debug_view == DebugView::IntersectionTests || count_rays;
+    if count_intersection_tests != primitive_state.count_intersection_tests
+        || count_rays != primitive_state.count_rays
+    {
+        primitive_state.count_intersection_tests = count_intersection_tests;
+        primitive_state.count_rays = count_rays;
+        primitive_state.pipeline_changed = true;
+    }
+
+    let mut pipeline_created = false;
+    if primitive_state.pipeline_changed {
+        let mut shader_defs = vec![];
+        if primitive_state.count_intersection_tests {
+            shader_defs.push("COUNT_INTERSECTION_TESTS");
+        }
+        if primitive_state.count_rays {
+            shader_defs.push("COUNT_RAYS");
+        }
+        match PrimitiveState::create_pipeline(
+            &render_state,
+            &primitive_state.primitive_buffers,
+            &shader_defs,
+            &primitive_state.shader_files,
+        ) {
+            Ok((bind_group_layout, ray_tracing_pipeline)) => {
+                primitive_state.bind_group_layout = bind_group_layout;
+                primitive_state.ray_tracing_pipeline = ray_tracing_pipeline;
+                primitive_state.pipeline_primitive_count = primitive_state.primitive_buffers.len();
+                pipeline_created = true;
+            }
+            // the previous pipeline keeps running until the shader is fixed, whether it was hot reloaded
+            // or a primitive with an invalid shader or layout was registered
+            Err(error) => render_state.errors.lock().unwrap().push(error),
+        }
+    }
+
+    // the bind group has to match the layout of the pipeline that runs, which is the previous one when creating it failed
+    if pipeline_created || primitive_state.buffers_changed {
+        primitive_state.bind_group = PrimitiveState::create_bind_group(
+            &render_state,
+            &primitive_state.bind_group_layout,
+            &primitive_state.materials,
+            &primitive_state.primitive_buffers[..primitive_state.pipeline_primitive_count],
+        );
+    }
+
+    primitive_state.pipeline_changed = false;
+    primitive_state.buffers_changed = false;
+}
+
+#[derive(Resource)]
+pub(super) struct GizmoState {
+    pipeline: wgpu::RenderPipeline,
+    vertices: GpuArrayBuffer<GpuGizmoVertex>,
+}
+
+impl FromWorld for GizmoState {
+    fn from_world(world: &mut World) -> Self {
+        let render_state = world.get_resource_mut::<RenderState>().unwrap();
+
+        let vertices = GpuArrayBuffer::new(
+            &render_state.device,
+            "Gizmo Vertex Buffer",
+            wgpu::ShaderStages::VERTEX,
+        );
+
+        let gizmo_pipeline_layout =
+            render_state
+                .device
+                .create_pipeline_layout(&wgpu::PipelineLayoutDescriptor {
+                    label: Some("Gizmo Pipeline Layout"),
+                    bind_group_layouts: &[
+                        &render_state.camera_bind_group_layout,
+                        &render_state.depth_texture_bind_group_layout,
+                        vertices.bind_group_layout(),
+                    ],
+                    push_constant_ranges: &[],
+                });
+
+        let gizmo_source = compose_shader(include_str!("./gizmos.wgsl"), &[]).unwrap();
+        let gizmo_shader = render_state
+            .device
+            .create_shader_module(wgpu::ShaderModuleDescriptor {
+                label: Some("gizmos.wgsl"),
+                source: wgpu::ShaderSource::Wgsl(gizmo_source.into()),
+            });
+        let gizmo_pipeline =
+            render_state
+                .device
+                .create_render_pipeline(&wgpu::RenderPipelineDescriptor {
+                    label: Some("Gizmo Pipeline"),
+                    layout: Some(&gizmo_pipeline_layout),
+                    vertex: wgpu::VertexState {
+                        module: &gizmo_shader,
+                        entry_point: "vertex",
+                        buffers: &[],
+                    },
+                    primitive: wgpu::PrimitiveState {
+                        topology: wgpu::PrimitiveTopology::LineList,
+                        ..Default::default()
+                    },
+                    depth_stencil: None,
+                    multisample: wgpu::MultisampleState::default(),
+                    fragment: Some(wgpu::FragmentState {
+                        module: &gizmo_shader,
+                        entry_point: "fragment",
+                        targets: &[Some(wgpu::ColorTargetState {
+                            format: MAIN_TEXTURE_FORMAT,
+                            blend: None,
+                            write_mask: wgpu::ColorWrites::ALL,
+                        })],
+                    }),
+                    multiview: None,
+                });
+
+        GizmoState {
+            pipeline: gizmo_pipeline,
+            vertices,
+        }
+    }
+}
+
+pub(super) fn update_gizmos(
+    render_state: Res<RenderState>,
+    mut gizmo_state: ResMut<GizmoState>,
+    gizmo_buffer: Res<GizmoBuffer>,
+) {
+    // the lines are drawn again every frame, so they always count as changed
+    gizmo_state.vertices.update(
+        &render_state.device,
+        &render_state.queue,
+        gizmo_buffer
+            .lines
+            .iter()
+            .flat_map(|&GizmoLine { start, end, color }| {
+                [
+                    GpuGizmoVertex {
+                        position: start,
+                        color,
+                    },
+                    GpuGizmoVertex {
+                        position: end,
+                        color,
+                    },
+                ]
+            })
+            .map(|vertex| (vertex, true)),
+    );
+    render_state.count_upload(gizmo_state.vertices.uploaded_bytes());
+}
+
+#[derive(Resource)]
+pub(super) struct OverlayState {
+    quad_buffer: wgpu::Buffer,
+    quad_bind_group: wgpu::BindGroup,
+    quads: Vec<GpuOverlayQuad>,
+    screen_size: (u32, u32),
+    buffer: Vec<u8>,
+}
+
+impl FromWorld for OverlayState {
+    fn from_world(world: &mut World) -> Self {
+        let render_state = world.get_resource_mut::<RenderState>().unwrap();
+
+        let quad_buffer = render_state.device.create_buffer(&wgpu::BufferDescriptor {
+            label: Some("Overlay Quad Buffer"),
+            size: GpuOverlayQuads::<'_>::min_size().get(),
+            usage: wgpu::BufferUsages::COPY_DST | wgpu::BufferUsages::STORAGE,
+            mapped_at_creation: false,
+        });
+
+        let quad_bind_group = render_state
+            .device
+            .create_bind_group(&wgpu::BindGroupDescriptor {
+                label: Some("Overlay Bind Group"),
+                layout: &render_state.overlay_bind_group_layout,
+                entries: &[wgpu::BindGroupEntry {
+                    binding: 0,
+                    resource: quad_buffer.as_entire_binding(),
+                }],
+            });
+
+        OverlayState {
+            quad_buffer,
+            quad_bind_group,
+            quads: vec![],
+            screen_size: (0, 0),
+            buffer: vec![],
+        }
+    }
+}
+
+pub(super) fn update_overlay(
+    render_state: Res<RenderState>,
+    mut overlay_state: ResMut<OverlayState>,
+    rects: Query<Ref<OverlayRect>>,
+    texts: Query<Ref<OverlayText>>,
+) {
+    let overlay_state: &mut OverlayState = &mut overlay_state;
+
+    let previous_quad_count = overlay_state.quads.len();
+    let screen_size = (
+        render_state.surface_config.width,
+        render_state.surface_config.height,
+    );
+
+    let mut components_changed = overlay_state.screen_size != screen_size;
+    overlay_state.screen_size = screen_size;
+    overlay_state.quads.clear();
+    rects.for_each(|rect| {
+        components_changed |= rect.is_changed();
+        let OverlayRect {
+            position,
+            size,
+            color,
+        } = *rect;
+        overlay_state.quads.push(GpuOverlayQuad {
+            position,
+            size,
+            color,
+            glyph: OVERLAY_SOLID_GLYPH,
+        });
+    });
+    texts.for_each(|text| {
+        components_changed |= text.is_changed();
+        let OverlayText {
+            ref text,
+            position,
+            scale,
+            color,
+        } = *text;
+        let glyph_size = FONT_GLYPH_SIZE as f32 * scale;
+        let mut cursor = position;
+        for character in text.chars() {
+            if character == '\n' {
+                cursor.x = position.x;
+                cursor.y += glyph_size;
+                continue;
+            }
+            if !character.is_whitespace() {
+                overlay_state.quads.push(GpuOverlayQuad {
+                    position: cursor,
+                    size: Vector2 {
+                        x: glyph_size,
+                        y: glyph_size,
+                    },
+                    color,
+                    glyph: if character.is_ascii() {
+                        character as u32
+                    } else {
+                        '?' as u32
+                    },
+                });
+            }
+            cursor.x += glyph_size;
+        }
+    });
+
+    if components_changed || overlay_state.quads.len() != previous_quad_count {
+        overlay_state.buffer.clear();
+        let mut buffer = StorageBuffer::new(&mut overlay_state.buffer);
+        buffer
+            .write(&GpuOverlayQuads {
+                screen_size: Vector2 {
+                    x: screen_size.0 as f32,
+                    y: screen_size.1 as f32,
+                },
+                length: ArrayLength,
+                data: &overlay_state.quads,
+            })
+            .unwrap();
+
+        if overlay_state.buffer.len() as wgpu::BufferAddress > overlay_state.quad_buffer.size() {
+            overlay_state.quad_buffer =
+                render_state.device.create_buffer(&wgpu::BufferDescriptor {
+                    label: Some("Overlay Quad Buffer"),
+                    size: overlay_state.buffer.len() as wgpu::BufferAddress,
+                    usage: wgpu::BufferUsages::COPY_DST | wgpu::BufferUsages::STORAGE,
+                    mapped_at_creation: false,
+                });
+
+            overlay_state.quad_bind_group =
+                render_state
+                    .device
+                    .create_bind_group(&wgpu::BindGroupDescriptor {
+                        label: Some("Overlay Bind Group"),
+                        layout: &render_state.overlay_bind_group_layout,
+                        entries: &[wgpu::BindGroupEntry {
+                            binding: 0,
+                            resource: overlay_state.quad_buffer.as_entire_binding(),
+                        }],
+                    });
+        }
+
+        render_state
+            .queue
+            .write_buffer(&overlay_state.quad_buffer, 0, &overlay_state.buffer);
+        render_state.count_upload(overlay_state.buffer.len() as u64);
+    }
+}
+
+#[derive(Resource)]
+pub(super) struct PostProcessState {
+    uniform_buffer: wgpu::Buffer,
+    bind_group: wgpu::BindGroup,
+    post_process: PostProcess,
+    color_grading: Option<ColorGrading>,
+    // the lut that is currently uploaded, this is used to avoid uploading the same lut again
+    lut: Option<Arc<Lut3d>>,
+    frame: u32,
+}
+
+impl PostProcessState {
+    fn create_bind_group(
+        render_state: &RenderState,
+        uniform_buffer: &wgpu::Buffer,
+        lut: &Lut3d,
+    ) -> wgpu::BindGroup {
+        let lut_texture = lut.create_texture(&render_state.device, &render_state.queue);
+        render_state
+            .device
+            .create_bind_group(&wgpu::BindGroupDescriptor {
+                label: Some("Post Process Settings Bind Group"),
+                layout: &render_state.post_process_bind_group_layout,
+                entries: &[
+                    wgpu::BindGroupEntry {
+                        binding: 0,
+                        resource: uniform_buffer.as_entire_binding(),
+                    },
+                    wgpu::BindGroupEntry {
+                        binding: 1,
+                        resource: wgpu::BindingResource::TextureView(
+                            &lut_texture.create_view(&wgpu::TextureViewDescriptor::default()),
+                        ),
+                    },
+                ],
+            })
+    }
+}
+
+impl FromWorld for PostProcessState {
+    fn from_world(world: &mut World) -> Self {
+        let render_state = world.get_resource_mut::<RenderState>().unwrap();
+
+        let uniform_buffer = render_state.device.create_buffer(&wgpu::BufferDescriptor {
+            label: Some("Post Process Uniform Buffer"),
+            size: GpuPostProcess::SHADER_SIZE.get(),
+            usage: wgpu::BufferUsages::COPY_DST | wgpu::BufferUsages::UNIFORM,
+            mapped_at_creation: false,
+        });
+
+        let bind_group =
+            Self::create_bind_group(&render_state, &uniform_buffer, &Lut3d::identity(2));
+
+        PostProcessState {
+            uniform_buffer,
+            bind_group,
+            post_process: PostProcess::default(),
+            color_grading: None,
+            lut: None,
+            frame: 0,
+        }
+    }
+}
+
+pub(super) fn update_post_process(
+    render_state: Res<RenderState>,
+    mut post_process_state: ResMut<PostProcessState>,
+    debug_view: Res<DebugView>,
+    camera: Query<(Option<&PostProcess>, Option<&ColorGrading>), With<MainCamera>>,
+) {
+    let post_process_state: &mut PostProcessState = &mut post_process_state;
+
+    // debug views show the raw output of the ray tracer
+    let (post_process, color_grading) = camera.single();
+    (
+        post_process_state.post_process,
+        post_process_state.color_grading,
+    ) = match *debug_view {
+        DebugView::Shaded => (
+            post_process.copied().unwrap_or_default(),
+            color_grading.cloned(),
+        ),
+        _ => (PostProcess::default(), None),
+    };
+    post_process_state.frame = post_process_state.frame.wrapping_add(1);
+
+    let lut = post_process_state
+        .color_grading
+        .as_ref()
+        .and_then(|color_grading| color_grading.lut.clone());
+    if let Some(lut) = lut {
+        if !post_process_state
+            .lut
+            .as_ref()
+            .is_some_and(|uploaded_lut| Arc::ptr_eq(uploaded_lut, &lut))
+        {
+            post_process_state.bind_group = PostProcessState::create_bind_group(
+                &render_state,
+                &post_process_state.uniform_buffer,
+                &lut,
+            );
+            post_process_state.lut = Some(lut);
+        }
+    }
+
+    let PostProcess {
+        bloom,
+        chromatic_aberration,
+        vignette,
+        film_grain,
+    } = post_process_state.post_process;
+    let bloom = bloom.unwrap_or_default();
+    let chromatic_aberration = chromatic_aberration.unwrap_or_default();
+    let vignette = vignette.unwrap_or_default();
+    let film_grain = film_grain.unwrap_or_default();
+
+    let color_grading = post_process_state.color_grading.clone().unwrap_or_default();
+    // without a lut the shader skips the lut pass, so the domain is unused
+    let (lut_domain_min, lut_domain_max) = match &post_process_state.lut {
+        Some(lut) => (lut.domain_min(), lut.domain_max()),
+        None => (
+            Vector3 {
+                x: 0.0,
+                y: 0.0,
+                z: 0.0,
+            },
+            Vector3 {
+                x: 1.0,
+                y: 1.0,
+                z: 1.0,
+            },
+        ),
+    };
+
+    let mut buffer = UniformBuffer::new([0; GpuPostProcess::SHADER_SIZE.get() as _]);
+    buffer
+        .write(&GpuPostProcess {
+            bloom_threshold: bloom.threshold,
+            bloom_intensity: bloom.intensity,
+            chromatic_aberration_intensity: chromatic_aberration.intensity,
+            vignette_intensity: vignette.intensity,
+            vignette_radius: vignette.radius,
+            vignette_smoothness: vignette.smoothness,
+            film_grain_intensity: film_grain.intensity,
+            frame: post_process_state.frame,
+            exposure: color_grading.exposure,
+            contrast: color_grading.contrast,
+            saturation: color_grading.saturation,
+            tonemapping: match color_grading.tonemapping {
+                Tonemapping::None => 0,
+                Tonemapping::Reinhard => 1,
+                Tonemapping::Aces => 2,
+            },
+            white_balance: color_grading.white_balance(),
+            lift: color_grading.lift,
+            gamma: color_grading.gamma,
+            gain: color_grading.gain,
+            lut_domain_min,
+            lut_domain_max,
+        })
+        .unwrap();
+    let bytes = buffer.into_inner();
+    render_state
+        .queue
+        .write_buffer(&post_process_state.uniform_buffer, 0, &bytes);
+    render_state.count_upload(bytes.len() as u64);
+}
+
+pub(super) fn update_camera(
+    render_state: Res<RenderState>,
+    debug_view: Res<DebugView>,
+    fog: Res<Fog>,
+    media_rendering: Res<MediaRendering>,
+    camera: Query<(Ref<GlobalTransform>, Ref<Camera>, Ref<MainCamera>)>,
+) {
+    let _span = info_span!("update_camera").entered();
+    let (global_transform, camera, main_camera) = camera.single();
+    if global_transform.is_changed()
+        || camera.is_changed()
+        || main_camera.is_changed()
+        || debug_view.is_changed()
+        || fog.is_changed()
+        || media_rendering.is_changed()
+    {
+        let mut buffer = UniformBuffer::new([0; GpuCamera::SHADER_SIZE.get() as _]);
+        let Camera {
+            v_fov,
+            min_distance,
+            max_distance,
+            sun_direction,
+            shutter_open,
+            shutter_close,
+        } = *camera;
+        let (media_rendering, max_bounces) = match *media_rendering {
+            MediaRendering::Analytic => (0, 0),
+            MediaRendering::PathTraced { max_bounces } => (1, max_bounces),
+        };
+        buffer
+            .write(&GpuCamera {
+                transform: global_transform.transform().motor,
+                v_fov,
+                min_distance,
+                max_distance,
+                sun_direction,
+                debug_view: *debug_view as u32,
+                shutter_open,
+                shutter_close,
+                fog_albedo: fog.albedo,
+                fog_density: fog.density,
+                fog_height: fog.height,
+                fog_height_falloff: fog.height_falloff,
+                fog_anisotropy: fog.anisotropy,
+                media_rendering,
+                max_bounces,
+            })
+            .unwrap();
+        let bytes = buffer.into_inner();
+        render_state
+            .queue
+            .write_buffer(&render_state.camera_uniform_buffer, 0, &bytes);
+        render_state.count_upload(bytes.len() as u64);
+    }
+}
+
+impl FromWorld for RenderGraph {
+    fn from_world(_world: &mut World) -> Self {
+        let mut render_graph = RenderGraph::new();
+
+        for (name, size, format, usage) in [
+            (
+                MAIN_TEXTURE,
+                TextureSize::Surface,
+                MAIN_TEXTURE_FORMAT,
+                wgpu::TextureUsages::STORAGE_BINDING
+                    | wgpu::TextureUsages::RENDER_ATTACHMENT
+                    | wgpu::TextureUsages::COPY_SRC,
+            ),
+            (
+                HDR_TEXTURE,
+                TextureSize::Scaled,
+                HDR_TEXTURE_FORMAT,
+                wgpu::TextureUsages::STORAGE_BINDING
+                    | wgpu::TextureUsages::TEXTURE_BINDING
+                    | wgpu::TextureUsages::COPY_SRC,
+            ),
+            (
+                DEPTH_TEXTURE,
+                TextureSize::Scaled,
+                wgpu::TextureFormat::R32Float,
+                wgpu::TextureUsages::STORAGE_BINDING
+                    | wgpu::TextureUsages::TEXTURE_BINDING
+                    | wgpu::TextureUsages::COPY_SRC,
+            ),
+        ] {
+            render_graph
+                .add_texture(
+                    name,
+                    GraphTexture {
+                        size,
+                        format,
+                        usage,
+                    },
+                )
+                .unwrap();
+        }
+
+        render_graph
+            .add_node(RAY_TRACING_NODE, RayTracingNode::default())
+            .unwrap();
+        render_graph
+            .add_node(POST_PROCESS_NODE, PostProcessNode::default())
+            .unwrap();
+        render_graph
+            .add_node(GIZMO_NODE, GizmoNode::default())
+            .unwrap();
+        render_graph.add_node(OVERLAY_NODE, OverlayNode).unwrap();
+        render_graph.add_node(PRESENT_NODE, PresentNode).unwrap();
+
+        render_graph
+            .add_node_edge(POST_PROCESS_NODE, GIZMO_NODE)
+            .unwrap();
+        render_graph
+            .add_node_edge(GIZMO_NODE, OVERLAY_NODE)
+            .unwrap();
+
+        render_graph
+    }
+}
+
+#[derive(Default)]
+struct RayTracingNode {
+    output_bind_group: Option<wgpu::BindGroup>,
+    // the tile that covers the whole hdr texture
+    tile_buffer: Option<wgpu::Buffer>,
+}
+
+impl RenderNode for RayTracingNode {
+    fn writes(&self) -> Vec<&'static str> {
+        vec![HDR_TEXTURE, DEPTH_TEXTURE]
+    }
+
+    fn prepare(&mut self, device: &wgpu::Device, resources: &RenderGraphResources, world: &World) {
+        let render_state = world.resource::<RenderState>();
+        let hdr_texture = resources.texture(HDR_TEXTURE);
+        let tile_buffer = device.create_buffer_init(&wgpu::util::BufferInitDescriptor {
+            label: Some("Render Tile Buffer"),
+            contents: &render_tile_bytes(GpuRenderTile {
+                offset_x: 0,
+                offset_y: 0,
+                image_width: hdr_texture.width(),
+                image_height: hdr_texture.height(),
+                samples: 1,
+                first_sample: 0,
+                total_samples: 1,
+            }),
+            usage: wgpu::BufferUsages::UNIFORM,
+        });
+        self.output_bind_group = Some(render_state.create_output_bind_group(
+            hdr_texture,
+            resources.texture(DEPTH_TEXTURE),
+            &tile_buffer,
+        ));
+        self.tile_buffer = Some(tile_buffer);
+    }
+
+    fn run(&mut self, context: &mut RenderContext<'_>, world: &World) {
+        let render_state = world.resource::<RenderState>();
+        let primitive_state = world.resource::<PrimitiveState>();
+        let hdr_texture = context.resources.texture(HDR_TEXTURE);
+
+        let timestamp_writes = context.compute_pass_timestamp_writes();
+        let mut ray_tracing_pass =
+            context
+                .encoder
+                .begin_compute_pass(&wgpu::ComputePassDescriptor {
+                    label: Some("Ray Tracing Pass"),
+                    timestamp_writes,
+                });
+
+        ray_tracing_pass.set_pipeline(&primitive_state.ray_tracing_pipeline);
+        ray_tracing_pass.set_bind_group(0, self.output_bind_group.as_ref().unwrap(), &[]);
+        ray_tracing_pass.set_bind_group(1, &render_state.camera_bind_group, &[]);
+        ray_tracing_pass.set_bind_group(2, &primitive_state.bind_group, &[]);
+        ray_tracing_pass.dispatch_workgroups(
+            (hdr_texture.width() + (16 - 1)) / 16,
+            (hdr_texture.height() + (16 - 1)) / 16,
+            1,
+        );
+    }
+}
+
+#[derive(Default)]
+struct PostProcessNode {
+    targets: Option<PostProcessTargets>,
+}
+
+impl RenderNode for PostProcessNode {
+    fn reads(&self) -> Vec<&'static str> {
+        vec![HDR_TEXTURE]
+    }
+
+    fn writes(&self) -> Vec<&'static str> {
+        vec![MAIN_TEXTURE]
+    }
+
+    fn prepare(&mut self, device: &wgpu::Device, resources: &RenderGraphResources, world: &World) {
+        self.targets = Some(PostProcessTargets::new(
+            device,
+            &world.resource::<RenderState>().post_process_pipelines,
+            resources.texture(HDR_TEXTURE),
+            resources.texture(MAIN_TEXTURE),
+        ));
+    }
+
+    fn run(&mut self, context: &mut RenderContext<'_>, world: &World) {
+        let render_state = world.resource::<RenderState>();
+        let post_process_state = world.resource::<PostProcessState>();
+
+        let timestamp_writes = context.compute_pass_timestamp_writes();
+        let mut post_process_pass =
+            context
+                .encoder
+                .begin_compute_pass(&wgpu::ComputePassDescriptor {
+                    label: Some("Post Process Pass"),
+                    timestamp_writes,
+                });
+        self.targets.as_ref().unwrap().record(
+            &mut post_process_pass,
+            &render_state.post_process_pipelines,
+            &post_process_state.bind_group,
+            &post_process_state.post_process,
+            post_process_state.color_grading.as_ref(),
+            *world.resource::<UpscaleFilter>(),
+        );
+    }
+}
+
+#[derive(Default)]
+struct GizmoNode {
+    depth_texture_bind_group: Option<wgpu::BindGroup>,
+}
+
+impl RenderNode for GizmoNode {
+    fn reads(&self) -> Vec<&'static str> {
+        vec![DEPTH_TEXTURE]
+    }
+
+    fn writes(&self) -> Vec<&'static str> {
+        vec![MAIN_TEXTURE]
+    }
+
+    fn prepare(&mut self, device: &wgpu::Device, resources: &RenderGraphResources, world: &World) {
+        self.depth_texture_bind_group = Some(
+            device.create_bind_group(&wgpu::BindGroupDescriptor {
+                label: Some("Depth Texture Bind Group"),
+                layout: &world
+                    .resource::<RenderState>()
+                    .depth_texture_bind_group_layout,
+                entries: &[wgpu::BindGroupEntry {
+                    binding: 0,
+                    resource: wgpu::BindingResource::TextureView(
+                        &resources
+                            .texture(DEPTH_TEXTURE)
+                            .create_view(&wgpu::TextureViewDescriptor::default()),
+                    ),
+                }],
+            }),
+        );
+    }
+
+    fn run(&mut self, context: &mut RenderContext<'_>, world: &World) {
+        let render_state = world.resource::<RenderState>();
+        let gizmo_state = world.resource::<GizmoState>();
+        if gizmo_state.vertices.is_empty() {
+            return;
+        }
+
+        let main_texture_view = context
+            .resources
+            .texture(MAIN_TEXTURE)
+            .create_view(&wgpu::TextureViewDescriptor::default());
+        let timestamp_writes = context.render_pass_timestamp_writes();
+        let mut gizmo_pass = context
+            .encoder
+            .begin_render_pass(&wgpu::RenderPassDescriptor {
+                label: Some("Gizmo Pass"),
+                color_attachments: &[Some(wgpu::RenderPassColorAttachment {
+                    view: &main_texture_view,
+                    resolve_target: None,
+                    ops: wgpu::Operations {
+                        load: wgpu::LoadOp::Load,
+                        store: wgpu::StoreOp::Store,
+                    },
+                })],
+                depth_stencil_attachment: None,
+                timestamp_writes,
+                occlusion_query_set: None,
+            });
+
+        gizmo_pass.set_pipeline(&gizmo_state.pipeline);
+        gizmo_pass.set_bind_group(0, &render_state.camera_bind_group, &[]);
+        gizmo_pass.set_bind_group(1, self.depth_texture_bind_group.as_ref().unwrap(), &[]);
+        gizmo_pass.set_bind_group(2, gizmo_state.vertices.bind_group(), &[]);
+        gizmo_pass.draw(0..gizmo_state.vertices.len() as u32, 0..1);
+    }
+}
+
+struct OverlayNode;
+
+impl RenderNode for OverlayNode {
+    fn writes(&self) -> Vec<&'static str> {
+        vec![MAIN_TEXTURE]
+    }
+
+    fn run(&mut self, context: &mut RenderContext<'_>, world: &World) {
+        let render_state = world.resource::<RenderState>();
+        let overlay_state = world.resource::<OverlayState>();
+        if overlay_state.quads.is_empty() {
+            return;
+        }
+
+        let main_texture_view = context
+            .resources
+            .texture(MAIN_TEXTURE)
+            .create_view(&wgpu::TextureViewDescriptor::default());
+        let timestamp_writes = context.render_pass_timestamp_writes();
+        let mut overlay_pass = context
+            .encoder
+            .begin_render_pass(&wgpu::RenderPassDescriptor {
+                label: Some("Overlay Pass"),
+                color_attachments: &[Some(wgpu::RenderPassColorAttachment {
+                    view: &main_texture_view,
+                    resolve_target: None,
+                    ops: wgpu::Operations {
+                        load: wgpu::LoadOp::Load,
+                        store: wgpu::StoreOp::Store,
+                    },
+                })],
+                depth_stencil_attachment: None,
+                timestamp_writes,
+                occlusion_query_set: None,
+            });
+
+        overlay_pass.set_pipeline(&render_state.overlay_pipeline);
+        overlay_pass.set_bind_group(0, &render_state.font_atlas_bind_group, &[]);
+        overlay_pass.set_bind_group(1, &overlay_state.quad_bind_group, &[]);
+        overlay_pass.draw(0..6, 0..overlay_state.quads.len() as u32);
+    }
+}
+
+struct PresentNode;
+
+impl RenderNode for PresentNode {
+    fn reads(&self) -> Vec<&'static str> {
+        vec![MAIN_TEXTURE]
+    }
+
+    fn run(&mut self, context: &mut RenderContext<'_>, _world: &World) {
+        let main_texture = context.resources.texture(MAIN_TEXTURE);
+        context.encoder.copy_texture_to_texture(
+            main_texture.as_image_copy(),
+            context.surface_texture.as_image_copy(),
+            wgpu::Extent3d {
+                width: main_texture.width(),
+                height: main_texture.height(),
+                depth_or_array_layers: 1,
+            },
+        );
+    }
+}
+
+pub(super) fn measure_diagnostics(
+    mut diagnostics: Diagnostics,
+    render_state: Res<RenderState>,
+    primitive_state: Res<PrimitiveState>,
+    render_graph: Res<RenderGraph>,
+) {
+    diagnostics.add_measurement(RenderDiagnostics::PRIMITIVE_COUNT, || {
+        primitive_state
+            .primitive_buffers
+            .iter()
+            .map(|primitive_buffer| primitive_buffer.count)
+            .sum::<usize>() as f64
+    });
+    let uploaded_bytes = render_state.uploaded_bytes.swap(0, Ordering::Relaxed);
+    diagnostics.add_measurement(RenderDiagnostics::UPLOADED_BYTES, || uploaded_bytes as f64);
+    diagnostics.add_measurement(RenderDiagnostics::SURFACE_CONFIGURATIONS, || {
+        render_state.surface_configurations as f64
+    });
+    diagnostics.add_measurement(RenderDiagnostics::RENDER_SCALE, || {
+        render_graph.resources().render_scale() as f64
+    });
+}
+
+// publishes the gpu times and ray statistics that were read back since the last frame
+fn read_diagnostics(world: &mut World) {
+    let mut render_state = world.resource_mut::<RenderState>();
+    render_state.device.poll(wgpu::Maintain::Poll);
+    let gpu_pass_times = render_state
+        .gpu_timer
+        .as_mut()
+        .and_then(|gpu_timer| gpu_timer.read());
+    if let Some((frame, times)) = &gpu_pass_times {
+        render_state.ray_tracing_time = times
+            .iter()
+            .find(|(node, _)| *node == RAY_TRACING_NODE)
+            .map(|&(_, milliseconds)| (*frame, milliseconds));
+    }
+    let ray_tracing_time = render_state.ray_tracing_time;
+    let ray_statistics = render_state.ray_counters.read();
+
+    let count_rays = world.contains_resource::<CountRays>();
+    let mut diagnostics = world.resource_mut::<RenderDiagnostics>();
+    if let Some((_, gpu_pass_times)) = gpu_pass_times {
+        diagnostics.gpu_pass_times = gpu_pass_times;
+    }
+    if !count_rays {
+        diagnostics.ray_statistics = None;
+    } else if ray_statistics.is_some() {
+        diagnostics.ray_statistics = ray_statistics;
+    }
+    // the timestamps and the counts of a frame can arrive in either order
+    if let Some(ray_statistics) = &mut diagnostics.ray_statistics {
+        ray_statistics.combine_ray_tracing_time(ray_tracing_time);
+    }
+}
+
+// copies the main texture of the frame that was just rendered to the recording, waiting for the gpu
+fn capture_frame(world: &mut World, render_graph: &RenderGraph, recorder: &Recorder) {
+    let _span = info_span!("capture_frame").entered();
+    world.resource_scope(|world, mut recording_state: Mut<RecordingState>| {
+        let render_state = world.resource::<RenderState>();
+        let main_texture = render_graph.resources().texture(MAIN_TEXTURE);
+        let (width, height) = (main_texture.width(), main_texture.height());
+        let bytes_per_row = (width * 4).next_multiple_of(wgpu::COPY_BYTES_PER_ROW_ALIGNMENT);
+        let size = bytes_per_row as u64 * height as u64;
+
+        let buffer_fits = match &recording_state.readback_buffer {
+            Some((_, buffer_size)) => *buffer_size == (width, height),
+            None => false,
+        };
+        if !buffer_fits {
+            recording_state.readback_buffer = Some((
+                ReadbackBuffer::new(&render_state.device, "Recording Readback Buffer", size),
+                (width, height),
+            ));
+        }
+        let (readback_buffer, _) = recording_state.readback_buffer.as_mut().unwrap();
+
+        let mut encoder =
+            render_state
+                .device
+                .create_command_encoder(&wgpu::CommandEncoderDescriptor {
+                    label: Some("Capture Encoder"),
+                });
+        encoder.copy_texture_to_buffer(
+            main_texture.as_image_copy(),
+            wgpu::ImageCopyBuffer {
+                buffer: &readback_buffer.buffer,
+                layout: wgpu::ImageDataLayout {
+                    offset: 0,
+                    bytes_per_row: Some(bytes_per_row),
+                    rows_per_image: None,
+                },
+            },
+            main_texture.size(),
+        );
+        render_state.queue.submit([encoder.finish()]);
+        readback_buffer.map(size);
+        render_state.device.poll(wgpu::Maintain::Wait);
+        let Some(Some(data)) = readback_buffer.read() else {
+            error!("failed to read a recorded frame back from the gpu");
+            return;
+        };
+
+        let mut pixels = Vec::with_capacity(width as usize * height as usize * 4);
+        for row in data.chunks_exact(bytes_per_row as usize) {
+            pixels.extend_from_slice(&row[..width as usize * 4]);
+        }
+        // copying to the surface doesn't convert the bytes even when it is srgb, so these are exactly the bytes the window shows
+        recording_state.write_frame(recorder, (width, height), pixels);
+    });
+}
+
+pub(super) fn render(world: &mut World) {
+    let _span = info_span!("render").entered();
+    read_diagnostics(world);
+    // rays are only counted while the counts of an earlier frame are not being read
+    let count_rays = world.resource::<PrimitiveState>().count_rays
+        && !world.resource::<RenderState>().ray_counters.is_pending();
+    let recorder = world.get_resource::<Recorder>().cloned();
+    let capture = match (&recorder, world.get_resource_mut::<RecordingState>()) {
+        (Some(recorder), Some(mut recording_state)) => recording_state.next_frame(recorder),
+        _ => false,
+    };
+
+    let errors = world.resource_scope(|world, mut render_graph: Mut<RenderGraph>| {
+        let output = loop {
+            let mut render_state = world.resource_mut::<RenderState>();
+            match render_state.surface.get_current_texture() {
+                Ok(output) => break output,
+                Err(error) => match error {
+                    e @ wgpu::SurfaceError::Timeout => {
+                        warn!("{e}");
+                        return vec![];
+                    }
+
+                    wgpu::SurfaceError::Outdated => {
+                        let size = render_state.window.inner_size();
+                        render_state.resize(size.width, size.height);
+                    }
+
+                    wgpu::SurfaceError::Lost => {
+                        render_state.configure_surface();
+                    }
+
+                    e @ wgpu::SurfaceError::OutOfMemory => {
+                        return vec![RenderError::DeviceLost(e.to_string())];
+                    }
+                },
+            }
+        };
+
+        let render_state = world.resource::<RenderState>();
+        render_graph.prepare(
+            &render_state.device,
+            (
+                render_state.surface_config.width,
+                render_state.surface_config.height,
+            ),
+            world,
+        );
+
+        let mut encoder =
+            render_state
+                .device
+                .create_command_encoder(&wgpu::CommandEncoderDescriptor {
+                    label: Some("Render Encoder"),
+                });
+        if count_rays {
+            render_state.ray_counters.clear(&mut encoder);
+        }
+        let timed_passes = render_graph.run(
+            &render_state.device,
+            &render_state.queue,
+            &mut encoder,
+            &output.texture,
+            render_state
+                .gpu_timer
+                .as_ref()
+                .and_then(|gpu_timer| gpu_timer.query_set()),
+            world,
+        );
+        if let Some(gpu_timer) = &render_state.gpu_timer {
+            gpu_timer.resolve(&mut encoder, &timed_passes);
+        }
+        if count_rays {
+            render_state.ray_counters.copy(&mut encoder);
+        }
+        render_state.queue.submit([encoder.finish()]);
+
+        render_state.window.pre_present_notify();
+        output.present();
+
+        if let (true, Some(recorder)) = (capture, &recorder) {
+            capture_frame(world, &render_graph, recorder);
+        }
+
+        let mut render_state = world.resource_mut::<RenderState>();
+        let frame = render_state.frames;
+        render_state.frames += 1;
+        if let Some(gpu_timer) = &mut render_state.gpu_timer {
+            gpu_timer.map(frame, timed_passes);
+        }
+        if count_rays {
+            render_state.ray_counters.map(frame);
+        }
+        let errors = std::mem::take(&mut *render_state.errors.lock().unwrap());
+        errors
+    });
+
+    for error in errors {
+        if matches!(error, RenderError::DeviceLost(_)) && world.contains_resource::<RenderState>() {
+            destroy_renderer(world);
+        }
+        report_error(world, error);
+    }
+}
+
+// the tiles are read back and a still is post processed as 4 f16 channels per pixel
+const _: () = assert!(matches!(
+    HDR_TEXTURE_FORMAT,
+    wgpu::TextureFormat::Rgba16Float
+));
+
+// every tile is copied to a buffer with rows of this many bytes, `bytes_per_row` of a copy has to be aligned
+fn tile_bytes_per_row(tile_size: u32) -> u32 {
+    let bytes_per_row = tile_size * HDR_TEXTURE_FORMAT.block_size(None).unwrap();
+    bytes_per_row.next_multiple_of(wgpu::COPY_BYTES_PER_ROW_ALIGNMENT)
+}
+
+pub(super) fn render_tiled(
+    world: &mut World,
+    tiled_render: &TiledRender,
+    mut progress: impl FnMut(TileProgress),
+) -> Result<RenderedImage, TiledRenderError> {
+    let _span = info_span!("render_tiled").entered();
+    let (Some(render_state), Some(primitive_state)) = (
+        world.get_resource::<RenderState>(),
+        world.get_resource::<PrimitiveState>(),
+    ) else {
+        return Err(TiledRenderError::Unavailable);
+    };
+
+    let TiledRender {
+        width,
+        height,
+        samples,
+        samples_per_submission,
+        tile_size,
+    } = *tiled_render;
+    if width == 0
+        || height == 0
+        || samples == 0
+        || samples_per_submission == 0
+        || tile_size == 0
+        || tile_size > render_state.device.limits().max_texture_dimension_2d
+    {
+        return Err(TiledRenderError::InvalidSize);
+    }
+
+    let device = &render_state.device;
+    let create_texture = |label, format| {
+        device.create_texture(&wgpu::TextureDescriptor {
+            label: Some(label),
+            size: wgpu::Extent3d {
+                width: tile_size,
+                height: tile_size,
+                depth_or_array_layers: 1,
+            },
+            mip_level_count: 1,
+            sample_count: 1,
+            dimension: wgpu::TextureDimension::D2,
+            format,
+            usage: wgpu::TextureUsages::STORAGE_BINDING | wgpu::TextureUsages::COPY_SRC,
+            view_formats: &[],
+        })
+    };
+    let tile_texture = create_texture("Tile Texture", HDR_TEXTURE_FORMAT);
+    let tile_depth_texture = create_texture("Tile Depth Texture", wgpu::TextureFormat::R32Float);
+    let tile_buffer = device.create_buffer(&wgpu::BufferDescriptor {
+        label: Some("Render Tile Buffer"),
+        size: GpuRenderTile::SHADER_SIZE.get(),
+        usage: wgpu::BufferUsages::UNIFORM | wgpu::BufferUsages::COPY_DST,
+        mapped_at_creation: false,
+    });
+    let output_bind_group =
+        render_state.create_output_bind_group(&tile_texture, &tile_depth_texture, &tile_buffer);
+
+    let bytes_per_pixel = HDR_TEXTURE_FORMAT.block_size(None).unwrap();
+    let bytes_per_row = tile_bytes_per_row(tile_size);
+    let readback_size = bytes_per_row as u64 * tile_size as u64;
+    let mut readback_buffer = ReadbackBuffer::new(device, "Tile Readback Buffer", readback_size);
+
+    let mut pixels = vec![[0.0; 4]; width as usize * height as usize];
+    let tile_count = tiled_render.tile_count();
+    for tile in 0..tile_count {
+        let x = tile % tiled_render.tiles_x() * tile_size;
+        let y = tile / tiled_render.tiles_x() * tile_size;
+
+        // the samples are split into submissions, which each average their samples, and their averages are added up weighted by their samples
+        let mut first_sample = 0;
+        while first_sample < samples {
+            let pass_samples = samples_per_submission.min(samples - first_sample);
+            render_state.queue.write_buffer(
+                &tile_buffer,
+                0,
+                &render_tile_bytes(GpuRenderTile {
+                    offset_x: x,
+                    offset_y: y,
+                    image_width: width,
+                    image_height: height,
+                    samples: pass_samples,
+                    first_sample,
+                    total_samples: samples,
+                }),
+            );
+
+            let mut encoder = device.create_command_encoder(&wgpu::CommandEncoderDescriptor {
+                label: Some("Tile Encoder"),
+            });
+            {
+                let mut ray_tracing_pass =
+                    encoder.begin_compute_pass(&wgpu::ComputePassDescriptor {
+                        label: Some("Tile Ray Tracing Pass"),
+                        timestamp_writes: None,
+                    });
+                ray_tracing_pass.set_pipeline(&primitive_state.ray_tracing_pipeline);
+                ray_tracing_pass.set_bind_group(0, &output_bind_group, &[]);
+                ray_tracing_pass.set_bind_group(1, &render_state.camera_bind_group, &[]);
+                ray_tracing_pass.set_bind_group(2, &primitive_state.bind_group, &[]);
+                ray_tracing_pass.dispatch_workgroups(
+                    tile_size.div_ceil(16),
+                    tile_size.div_ceil(16),
+                    1,
+                );
+            }
+            encoder.copy_texture_to_buffer(
+                tile_texture.as_image_copy(),
+                wgpu::ImageCopyBuffer {
+                    buffer: &readback_buffer.buffer,
+                    layout: wgpu::ImageDataLayout {
+                        offset: 0,
+                        bytes_per_row: Some(bytes_per_row),
+                        rows_per_image: None,
+                    },
+                },
+                tile_texture.size(),
+            );
+            render_state.queue.submit([encoder.finish()]);
+
+            // waiting for every submission keeps the gpu from queueing more work than it can finish before a timeout
+            readback_buffer.map(readback_size);
+            device.poll(wgpu::Maintain::Wait);
+            let data = readback_buffer
+                .read()
+                .flatten()
+                .ok_or(TiledRenderError::Readback)?;
+
+            let weight = pass_samples as f32 / samples as f32;
+            for row in 0..tile_size.min(height - y) {
+                for column in 0..tile_size.min(width - x) {
+                    let offset = (row * bytes_per_row + column * bytes_per_pixel) as usize;
+                    let pixel =
+                        &mut pixels[(y + row) as usize * width as usize + (x + column) as usize];
+                    for (channel, bytes) in pixel
+                        .iter_mut()
+                        .zip(data[offset..offset + bytes_per_pixel as usize].chunks_exact(2))
+                    {
+                        *channel += f16_to_f32(u16::from_le_bytes([bytes[0], bytes[1]])) * weight;
+                    }
+                }
+            }
+            first_sample += pass_samples;
+        }
+        progress(TileProgress {
+            finished_tiles: tile + 1,
+            tile_count,
+        });
+    }
+
+    Ok(RenderedImage {
+        width,
+        height,
+        pixels,
+    })
+}
+
+pub(super) fn post_process_image(
+    world: &World,
+    image: &RenderedImage,
+) -> Result<image::RgbaImage, TiledRenderError> {
+    let _span = info_span!("post_process_image").entered();
+    let (Some(render_state), Some(post_process_state)) = (
+        world.get_resource::<RenderState>(),
+        world.get_resource::<PostProcessState>(),
+    ) else {
+        return Err(TiledRenderError::Unavailable);
+    };
+
+    let RenderedImage {
+        width,
+        height,
+        ref pixels,
+    } = *image;
+    let device = &render_state.device;
+    if width == 0 || height == 0 || width.max(height) > device.limits().max_texture_dimension_2d {
+        return Err(TiledRenderError::InvalidSize);
+    }
+
+    let size = wgpu::Extent3d {
+        width,
+        height,
+        depth_or_array_layers: 1,
+    };
+    let create_texture = |label, format, usage| {
+        device.create_texture(&wgpu::TextureDescriptor {
+            label: Some(label),
+            size,
+            mip_level_count: 1,
+            sample_count: 1,
+            dimension: wgpu::TextureDimension::D2,
+            format,
+            usage: wgpu::TextureUsages::STORAGE_BINDING | usage,
+            view_formats: &[],
+        })
+    };
+    let hdr_texture = create_texture(
+        "Still HDR Texture",
+        HDR_TEXTURE_FORMAT,
+        wgpu::TextureUsages::TEXTURE_BINDING | wgpu::TextureUsages::COPY_DST,
+    );
+    let main_texture = create_texture(
+        "Still Main Texture",
+        MAIN_TEXTURE_FORMAT,
+        wgpu::TextureUsages::COPY_SRC,
+    );
+
+    let hdr_bytes = pixels
+        .iter()
+        .flatten()
+        .flat_map(|&channel| f32_to_f16(channel).to_le_bytes())
+        .collect::<Vec<_>>();
+    render_state.queue.write_texture(
+        hdr_texture.as_image_copy(),
+        &hdr_bytes,
+        wgpu::ImageDataLayout {
+            offset: 0,
+            bytes_per_row: Some(width * HDR_TEXTURE_FORMAT.block_size(None).unwrap()),
+            rows_per_image: None,
+        },
+        size,
+    );
+
+    let targets = PostProcessTargets::new(
+        device,
+        &render_state.post_process_pipelines,
+        &hdr_texture,
+        &main_texture,
+    );
+    let bytes_per_pixel = MAIN_TEXTURE_FORMAT.block_size(None).unwrap();
+    let bytes_per_row =
+        (width * bytes_per_pixel).next_multiple_of(wgpu::COPY_BYTES_PER_ROW_ALIGNMENT);
+    let readback_size = bytes_per_row as u64 * height as u64;
+    let mut readback_buffer = ReadbackBuffer::new(device, "Still Readback Buffer", readback_size);
+
+    let mut encoder = device.create_command_encoder(&wgpu::CommandEncoderDescriptor {
+        label: Some("Still Post Process Encoder"),
+    });
+    {
+        let mut post_process_pass = encoder.begin_compute_pass(&wgpu::ComputePassDescriptor {
+            label: Some("Still Post Process Pass"),
+            timestamp_writes: None,
+        });
+        // the image has the size of the main texture, so the upscale filter makes no difference
+        targets.record(
+            &mut post_process_pass,
+            &render_state.post_process_pipelines,
+            &post_process_state.bind_group,
+            &post_process_state.post_process,
+            post_process_state.color_grading.as_ref(),
+            UpscaleFilter::Bilinear,
+        );
+    }
+    encoder.copy_texture_to_buffer(
+        main_texture.as_image_copy(),
+        wgpu::ImageCopyBuffer {
+            buffer: &readback_buffer.buffer,
+            layout: wgpu::ImageDataLayout {
+                offset: 0,
+                bytes_per_row: Some(bytes_per_row),
+                rows_per_image: None,
+            },
+        },
+        size,
+    );
+    render_state.queue.submit([encoder.finish()]);
+
+    readback_buffer.map(readback_size);
+    device.poll(wgpu::Maintain::Wait);
+    let data = readback_buffer
+        .read()
+        .flatten()
+        .ok_or(TiledRenderError::Readback)?;
+
+    // the main texture is shown in the window as it is, so its bytes are saved without conversion
+    const _: () = assert!(matches!(
+        MAIN_TEXTURE_FORMAT,
+        wgpu::TextureFormat::Rgba8Unorm
+    ));
+    let row_size = (width * bytes_per_pixel) as usize;
+    let bytes = data
+        .chunks_exact(bytes_per_row as usize)
+        .flat_map(|row| &row[..row_size])
+        .copied()
+        .collect();
+    Ok(image::RgbaImage::from_raw(width, height, bytes).unwrap())
 }
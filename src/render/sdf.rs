@@ -0,0 +1,291 @@
+use crate::{
+    math::{Motor, Point, Vector3},
+    render::{MaterialHandle, Primitive},
+    transform::{GlobalTransform, PreviousGlobalTransform},
+};
+use bevy::{
+    ecs::{
+        component::Component,
+        entity::Entity,
+        query::Has,
+        system::{Local, Query},
+    },
+    hierarchy::Children,
+    log::warn,
+};
+use encase::ShaderType;
+
+/// how many shapes an [`Sdf`] can have, the ones after are ignored with a warning
+pub const MAX_SDF_SHAPES: usize = 8;
+
+/// a primitive made of signed distance fields, which can blend into each other unlike the analytic primitives,
+/// it is rendered by sphere tracing, so it is slower to intersect
+///
+/// the shapes are the [`SdfShape`] of the entity followed by the [`SdfShape`]s of its descendants, depth first in the order of the children,
+/// they are posed by their [`GlobalTransform`] relative to the entity, so shapes on different entities blend into each other,
+/// a descendant that is an [`Sdf`] itself is skipped with its descendants, because their shapes belong to it,
+/// they are combined in order starting from nothing, so the first shape should be a union
+#[derive(Component, Clone, Debug, Default)]
+pub struct Sdf {
+    // the shapes and their transforms relative to the entity, collected by `collect_sdf_shapes`
+    shapes: Vec<(SdfShape, Motor)>,
+}
+
+/// a shape of the [`Sdf`] of the entity or of one of its ancestors
+#[derive(Component, Clone, Copy, Debug, PartialEq)]
+pub struct SdfShape {
+    pub primitive: SdfPrimitive,
+    pub operation: SdfOperation,
+    /// how far the shape blends into the shapes before it, 0 for sharp edges
+    pub smoothness: f32,
+}
+
+/// a shape centered on the origin, the torus, capsule and cylinder are around the y axis
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum SdfPrimitive {
+    Box {
+        half_size: Vector3,
+    },
+    /// a box with its edges and corners rounded by `radius`, it still fits into `half_size`
+    RoundedBox {
+        half_size: Vector3,
+        radius: f32,
+    },
+    Torus {
+        major_radius: f32,
+        minor_radius: f32,
+    },
+    /// the line from `-half_length` to `half_length` on the y axis widened by `radius`
+    Capsule {
+        half_length: f32,
+        radius: f32,
+    },
+    Cylinder {
+        half_height: f32,
+        radius: f32,
+    },
+}
+
+/// how a shape is combined with the shapes before it
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum SdfOperation {
+    #[default]
+    Union,
+    /// cuts the shape out of the shapes before it
+    Subtract,
+    /// keeps only where the shape overlaps the shapes before it
+    Intersect,
+}
+
+impl SdfPrimitive {
+    // the size and radius of the shape on the gpu
+    fn gpu_size_and_radius(self) -> (Vector3, f32) {
+        let y = |y| Vector3 { x: 0.0, y, z: 0.0 };
+        match self {
+            SdfPrimitive::Box { half_size } => (half_size, 0.0),
+            SdfPrimitive::RoundedBox { half_size, radius } => (half_size, radius),
+            SdfPrimitive::Torus {
+                major_radius,
+                minor_radius,
+            } => (
+                Vector3 {
+                    x: major_radius,
+                    y: 0.0,
+                    z: 0.0,
+                },
+                minor_radius,
+            ),
+            SdfPrimitive::Capsule {
+                half_length,
+                radius,
+            } => (y(half_length), radius),
+            SdfPrimitive::Cylinder {
+                half_height,
+                radius,
+            } => (y(half_height), radius),
+        }
+    }
+
+    // the radius of a sphere around the origin that contains the shape
+    fn bounding_radius(self) -> f32 {
+        match self {
+            SdfPrimitive::Box { half_size } | SdfPrimitive::RoundedBox { half_size, .. } => {
+                half_size.length()
+            }
+            SdfPrimitive::Torus {
+                major_radius,
+                minor_radius,
+            } => major_radius + minor_radius,
+            SdfPrimitive::Capsule {
+                half_length,
+                radius,
+            } => half_length + radius,
+            SdfPrimitive::Cylinder {
+                half_height,
+                radius,
+            } => (half_height * half_height + radius * radius).sqrt(),
+        }
+    }
+}
+
+#[derive(ShaderType)]
+struct GpuSdfShape {
+    // from the space of the entity to the space of the shape
+    inverse_transform: Motor,
+    size: Vector3,
+    radius: f32,
+    primitive: u32,
+    operation: u32,
+    smoothness: f32,
+}
+
+#[derive(ShaderType)]
+pub struct GpuSdf {
+    transform: Motor,
+    previous_transform: Motor,
+    shapes: [GpuSdfShape; MAX_SDF_SHAPES],
+    shape_count: u32,
+    bounding_radius: f32,
+    material: u32,
+}
+
+impl Primitive for Sdf {
+    type GpuData = GpuSdf;
+
+    const SHADER_TYPE: &'static str = "Sdf";
+    const SHADER_INTERSECT: &'static str = "intersect_sdf";
+    const SHADER: &'static str = include_str!("./sdf.wgsl");
+    const SHADER_FILE: Option<&'static str> = Some("sdf.wgsl");
+    // the shapes are read from the storage buffer while marching instead of copying them for every ray
+    const SHADER_INTERSECT_BY_INDEX: bool = true;
+
+    fn gpu_data(
+        &self,
+        transform: &GlobalTransform,
+        previous_transform: &PreviousGlobalTransform,
+        material: MaterialHandle,
+    ) -> Self::GpuData {
+        let shapes = &self.shapes[..self.shapes.len().min(MAX_SDF_SHAPES)];
+
+        // subtracting and intersecting only removes from the shapes before, smooth unions can bulge out by the smoothness
+        let bounding_radius = shapes
+            .iter()
+            .filter(|(shape, _)| shape.operation == SdfOperation::Union)
+            .map(|(shape, shape_transform)| {
+                let offset = Vector3::from(Point::IDENTITY.transform(*shape_transform));
+                offset.length() + shape.primitive.bounding_radius() + shape.smoothness
+            })
+            .fold(0.0, f32::max);
+
+        GpuSdf {
+            transform: transform.transform().motor,
+            previous_transform: previous_transform.transform().motor,
+            shapes: std::array::from_fn(|index| {
+                let Some((shape, shape_transform)) = shapes.get(index) else {
+                    return GpuSdfShape {
+                        inverse_transform: Motor::IDENTITY,
+                        size: Vector3 {
+                            x: 0.0,
+                            y: 0.0,
+                            z: 0.0,
+                        },
+                        radius: 0.0,
+                        primitive: 0,
+                        operation: 0,
+                        smoothness: 0.0,
+                    };
+                };
+                let (size, radius) = shape.primitive.gpu_size_and_radius();
+                GpuSdfShape {
+                    inverse_transform: shape_transform.inverse(),
+                    size,
+                    radius,
+                    primitive: match shape.primitive {
+                        SdfPrimitive::Box { .. } => 0,
+                        SdfPrimitive::RoundedBox { .. } => 1,
+                        SdfPrimitive::Torus { .. } => 2,
+                        SdfPrimitive::Capsule { .. } => 3,
+                        SdfPrimitive::Cylinder { .. } => 4,
+                    },
+                    operation: shape.operation as u32,
+                    smoothness: shape.smoothness,
+                }
+            }),
+            shape_count: shapes.len() as u32,
+            bounding_radius,
+            material: material.index(),
+        }
+    }
+}
+
+type SdfQuery<'a> = (
+    Entity,
+    &'a mut Sdf,
+    &'a GlobalTransform,
+    Option<&'a SdfShape>,
+    Option<&'a Children>,
+);
+
+type Descendant<'a> = (
+    Option<&'a SdfShape>,
+    Option<&'a GlobalTransform>,
+    Option<&'a Children>,
+    Has<Sdf>,
+);
+
+// gathers the shapes of every sdf from the entity and its descendants, the sdf only changes when they did
+pub(super) fn collect_sdf_shapes(
+    mut sdfs: Query<SdfQuery>,
+    descendants: Query<Descendant>,
+    mut shapes: Local<Vec<(SdfShape, Motor)>>,
+) {
+    sdfs.for_each_mut(|(entity, mut sdf, global_transform, shape, children)| {
+        shapes.clear();
+        shapes.extend(shape.map(|&shape| (shape, Motor::IDENTITY)));
+        if let Some(children) = children {
+            collect_descendant_shapes(
+                children,
+                global_transform.transform().motor.inverse(),
+                &descendants,
+                &mut shapes,
+            );
+        }
+
+        if sdf.shapes != *shapes {
+            if shapes.len() > MAX_SDF_SHAPES {
+                warn!(
+                    "the sdf of {entity:?} has {} shapes, only the first {MAX_SDF_SHAPES} are rendered",
+                    shapes.len()
+                );
+            }
+            sdf.shapes.clone_from(&shapes);
+        }
+    });
+}
+
+// `inverse_motor` is the inverse of the global transform of the sdf, so the shapes are relative to it
+fn collect_descendant_shapes(
+    children: &Children,
+    inverse_motor: Motor,
+    descendants: &Query<Descendant>,
+    shapes: &mut Vec<(SdfShape, Motor)>,
+) {
+    for &child in children {
+        let Ok((shape, global_transform, children, is_sdf)) = descendants.get(child) else {
+            continue;
+        };
+        if is_sdf {
+            continue;
+        }
+        // an entity without a global transform yet is added once it has one
+        if let (Some(&shape), Some(global_transform)) = (shape, global_transform) {
+            shapes.push((
+                shape,
+                inverse_motor.apply(global_transform.transform().motor),
+            ));
+        }
+        if let Some(children) = children {
+            collect_descendant_shapes(children, inverse_motor, descendants, shapes);
+        }
+    }
+}
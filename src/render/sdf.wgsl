@@ -0,0 +1,156 @@
+// has to match `SdfPrimitive`
+const SDF_BOX: u32 = 0u;
+const SDF_ROUNDED_BOX: u32 = 1u;
+const SDF_TORUS: u32 = 2u;
+const SDF_CAPSULE: u32 = 3u;
+const SDF_CYLINDER: u32 = 4u;
+
+// has to match `SdfOperation`
+const SDF_UNION: u32 = 0u;
+const SDF_SUBTRACT: u32 = 1u;
+const SDF_INTERSECT: u32 = 2u;
+
+const SDF_MAX_STEPS: u32 = 128u;
+// a ray hits the surface when it gets closer than this
+const SDF_HIT_DISTANCE: f32 = 0.0005;
+// hit positions are moved this far out of the surface, so rays sent from them don't hit it right away
+const SDF_SURFACE_OFFSET: f32 = 0.002;
+const SDF_NORMAL_EPSILON: f32 = 0.0005;
+// the distance to nothing, not infinite so blending with it stays finite
+const SDF_EMPTY: f32 = 1.0e9;
+
+struct SdfShape {
+    inverse_transform: Motor,
+    size: vec3<f32>,
+    radius: f32,
+    primitive: u32,
+    operation: u32,
+    smoothness: f32,
+}
+
+struct Sdf {
+    transform: Motor,
+    previous_transform: Motor,
+    // `MAX_SDF_SHAPES`
+    shapes: array<SdfShape, 8>,
+    shape_count: u32,
+    // a sphere around the origin that contains every shape, the shapes are only marched inside of it
+    bounding_radius: f32,
+    material: u32,
+}
+
+fn sdf_box(position: vec3<f32>, half_size: vec3<f32>) -> f32 {
+    let q = abs(position) - half_size;
+    return length(max(q, vec3<f32>(0.0))) + min(max(q.x, max(q.y, q.z)), 0.0);
+}
+
+fn sdf_torus(position: vec3<f32>, major_radius: f32, minor_radius: f32) -> f32 {
+    let q = vec2<f32>(length(position.xz) - major_radius, position.y);
+    return length(q) - minor_radius;
+}
+
+fn sdf_capsule(position: vec3<f32>, half_length: f32, radius: f32) -> f32 {
+    var p = position;
+    p.y -= clamp(p.y, -half_length, half_length);
+    return length(p) - radius;
+}
+
+fn sdf_cylinder(position: vec3<f32>, half_height: f32, radius: f32) -> f32 {
+    let d = abs(vec2<f32>(length(position.xz), position.y)) - vec2<f32>(radius, half_height);
+    return min(max(d.x, d.y), 0.0) + length(max(d, vec2<f32>(0.0)));
+}
+
+fn sdf_shape_distance(shape: SdfShape, position: vec3<f32>) -> f32 {
+    let p = point_to_vec3(transform_point(vec3_to_point(position), shape.inverse_transform));
+    switch shape.primitive {
+        case SDF_BOX: {
+            return sdf_box(p, shape.size);
+        }
+        case SDF_ROUNDED_BOX: {
+            return sdf_box(p, shape.size - shape.radius) - shape.radius;
+        }
+        case SDF_TORUS: {
+            return sdf_torus(p, shape.size.x, shape.radius);
+        }
+        case SDF_CAPSULE: {
+            return sdf_capsule(p, shape.size.y, shape.radius);
+        }
+        default: {
+            return sdf_cylinder(p, shape.size.y, shape.radius);
+        }
+    }
+}
+
+// the polynomial smooth minimum, the distances blend when they are less than `smoothness` apart
+fn sdf_smooth_min(a: f32, b: f32, smoothness: f32) -> f32 {
+    if smoothness <= 0.0 {
+        return min(a, b);
+    }
+    let h = clamp(0.5 + 0.5 * (b - a) / smoothness, 0.0, 1.0);
+    return mix(b, a, h) - smoothness * h * (1.0 - h);
+}
+
+// the distance to the sdf at `index` in `primitives_Sdf`, the storage buffer the renderer declares for this type
+fn sdf_distance(index: u32, position: vec3<f32>) -> f32 {
+    var distance = SDF_EMPTY;
+    let shape_count = primitives_Sdf.data[index].value.shape_count;
+    for (var shape_index = 0u; shape_index < shape_count; shape_index += 1u) {
+        let shape = primitives_Sdf.data[index].value.shapes[shape_index];
+        let shape_distance = sdf_shape_distance(shape, position);
+        // intersecting is the union of the insides, subtracting is intersecting with the inside of the shape
+        let outside = select(-1.0, 1.0, shape.operation == SDF_UNION);
+        let shape_outside = select(1.0, -1.0, shape.operation == SDF_INTERSECT);
+        distance = outside * sdf_smooth_min(outside * distance, shape_outside * shape_distance, shape.smoothness);
+    }
+    return distance;
+}
+
+// gets the index instead of a copy of the sdf, so the shapes are only read from the storage buffer when they are needed
+fn intersect_sdf(ray: Ray, index: u32) -> Hit {
+    var hit: Hit;
+    hit.hit = false;
+    hit.material = primitives_Sdf.data[index].value.material;
+
+    // the ray is marched in the space of the entity
+    let transform = interpolate_motor(primitives_Sdf.data[index].value.previous_transform, primitives_Sdf.data[index].value.transform, ray.time);
+    let inverse_transform = inverse_motor(transform);
+    let origin = point_to_vec3(transform_point(vec3_to_point(ray.origin), inverse_transform));
+    let local_direction = point_to_vec3(transform_point(vec3_to_point(ray.direction), rotation_part_of_motor(inverse_transform)));
+    let scale = length(local_direction);
+    let direction = local_direction / scale;
+
+    let half_b = dot(origin, direction);
+    let bounding_radius = primitives_Sdf.data[index].value.bounding_radius;
+    let c = dot(origin, origin) - bounding_radius * bounding_radius;
+    let discriminant = half_b * half_b - c;
+    if discriminant < 0.0 {
+        return hit;
+    }
+    let sqrt_discriminant = sqrt(discriminant);
+    let end = min(-half_b + sqrt_discriminant, camera.max_distance * scale);
+    var t = max(-half_b - sqrt_discriminant, camera.min_distance * scale);
+
+    for (var step = 0u; step < SDF_MAX_STEPS && t <= end; step += 1u) {
+        let distance = sdf_distance(index, origin + direction * t);
+        if distance < SDF_HIT_DISTANCE {
+            hit.hit = true;
+            break;
+        }
+        t += distance;
+    }
+    if !hit.hit {
+        return hit;
+    }
+
+    // the gradient of the distance from four points around the hit
+    let position = origin + direction * t;
+    let e = vec2<f32>(1.0, -1.0) * SDF_NORMAL_EPSILON;
+    let local_normal = normalize(
+        e.xyy * sdf_distance(index, position + e.xyy) + e.yyx * sdf_distance(index, position + e.yyx) + e.yxy * sdf_distance(index, position + e.yxy) + e.xxx * sdf_distance(index, position + e.xxx)
+    );
+
+    hit.distance = t / scale;
+    hit.normal = point_to_vec3(transform_point(vec3_to_point(local_normal), rotation_part_of_motor(transform)));
+    hit.position = ray.origin + ray.direction * hit.distance + hit.normal * SDF_SURFACE_OFFSET;
+    return hit;
+}
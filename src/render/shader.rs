@@ -0,0 +1,268 @@
+use bevy::ecs::system::Resource;
+use std::{
+    collections::{HashMap, HashSet},
+    fmt, fs, io,
+    path::{Path, PathBuf},
+    time::{Duration, Instant, SystemTime},
+};
+
+/// the wgsl modules that shaders can include with `#import <name>`, they are in `src/render/<name>.wgsl`
+pub(super) const MODULES: &[(&str, &str)] = &[
+    ("math", include_str!("./math.wgsl")),
+    ("camera", include_str!("./camera.wgsl")),
+];
+
+/// resolves the preprocessor directives of a wgsl shader
+///
+/// - `#import <name>` is replaced with the module called `name`, every module is included at most once
+/// - `#ifdef <name>`, `#ifndef <name>`, `#else` and `#endif` keep or remove lines depending on whether `name` is in `shader_defs`
+///
+/// directives have to be on their own line
+pub fn compose_shader(source: &str, shader_defs: &[&str]) -> Result<String, ShaderComposeError> {
+    compose_shader_with_modules(source, shader_defs, |_, module_source| module_source)
+}
+
+/// like [`compose_shader`], but the source of every imported module is given by `module_source`,
+/// which gets the name of the module and the source that was compiled in
+pub(super) fn compose_shader_with_modules<'a>(
+    source: &str,
+    shader_defs: &[&str],
+    module_source: impl Fn(&'static str, &'static str) -> &'a str,
+) -> Result<String, ShaderComposeError> {
+    let mut composer = Composer {
+        shader_defs,
+        module_source: &module_source,
+        imported: HashSet::new(),
+        output: String::with_capacity(source.len()),
+    };
+    composer.compose(None, source)?;
+    Ok(composer.output)
+}
+
+/// recompiles the ray tracing shader when a `.wgsl` file in [`ShaderHotReload::directory`] changes, insert this resource to enable it during development
+///
+/// when the changed shader does not compile, the error is printed and the previous pipeline is kept
+#[derive(Resource)]
+pub struct ShaderHotReload {
+    /// by default the `src/render` directory of this crate
+    pub directory: PathBuf,
+    /// how often the files are checked for changes, by default twice a second
+    pub interval: Duration,
+    // the last modification time of every file, to notice changes
+    modified: HashMap<PathBuf, SystemTime>,
+    last_check: Option<Instant>,
+}
+
+impl Default for ShaderHotReload {
+    fn default() -> Self {
+        Self {
+            directory: Path::new(env!("CARGO_MANIFEST_DIR")).join("src/render"),
+            interval: Duration::from_millis(500),
+            modified: HashMap::new(),
+            last_check: None,
+        }
+    }
+}
+
+impl ShaderHotReload {
+    // whether a file was changed or added since the last check, the files are only checked every `interval`,
+    // the first call always returns true
+    pub(super) fn files_changed(&mut self) -> bool {
+        let now = Instant::now();
+        if let Some(last_check) = self.last_check {
+            if now.duration_since(last_check) < self.interval {
+                return false;
+            }
+        }
+        let mut changed = self.last_check.is_none();
+        self.last_check = Some(now);
+
+        let Ok(entries) = fs::read_dir(&self.directory) else {
+            return changed;
+        };
+        for path in entries.filter_map(|entry| Some(entry.ok()?.path())) {
+            if path
+                .extension()
+                .is_some_and(|extension| extension == "wgsl")
+            {
+                let Ok(modified) = path.metadata().and_then(|metadata| metadata.modified()) else {
+                    continue;
+                };
+                changed |= self.modified.insert(path, modified) != Some(modified);
+            }
+        }
+        changed
+    }
+}
+
+/// the wgsl files of the renderer that were read from disk instead of the ones that were compiled in
+#[derive(Default)]
+pub(super) struct ShaderFiles {
+    files: HashMap<String, String>,
+}
+
+impl ShaderFiles {
+    /// reads every `.wgsl` file in `directory`
+    pub(super) fn read(directory: &Path) -> io::Result<Self> {
+        let mut files = HashMap::new();
+        for entry in fs::read_dir(directory)? {
+            let path = entry?.path();
+            if path
+                .extension()
+                .is_some_and(|extension| extension == "wgsl")
+            {
+                if let Some(name) = path.file_name().and_then(|name| name.to_str()) {
+                    files.insert(name.to_string(), fs::read_to_string(&path)?);
+                }
+            }
+        }
+        Ok(Self { files })
+    }
+
+    /// the file called `name`, or `builtin` if it was not read from disk
+    pub(super) fn get<'a>(&'a self, name: &str, builtin: &'a str) -> &'a str {
+        self.files.get(name).map_or(builtin, String::as_str)
+    }
+
+    /// composes `source` with the modules of these files
+    pub(super) fn compose(
+        &self,
+        source: &str,
+        shader_defs: &[&str],
+    ) -> Result<String, ShaderComposeError> {
+        compose_shader_with_modules(source, shader_defs, |name, builtin| {
+            self.get(&format!("{name}.wgsl"), builtin)
+        })
+    }
+}
+
+struct Composer<'a, 'm> {
+    shader_defs: &'a [&'a str],
+    module_source: &'a dyn Fn(&'static str, &'static str) -> &'m str,
+    imported: HashSet<&'static str>,
+    output: String,
+}
+
+impl Composer<'_, '_> {
+    fn compose(
+        &mut self,
+        module: Option<&'static str>,
+        source: &str,
+    ) -> Result<(), ShaderComposeError> {
+        // for every `#ifdef` that was not closed yet, whether its lines are kept
+        let mut conditions: Vec<bool> = vec![];
+
+        for (index, line) in source.lines().enumerate() {
+            let location = ShaderLocation {
+                module,
+                line: index + 1,
+            };
+            let Some(directive) = line.trim_start().strip_prefix('#') else {
+                if conditions.iter().all(|&keep| keep) {
+                    self.output += line;
+                    self.output.push('\n');
+                }
+                continue;
+            };
+
+            let mut words = directive.split_whitespace();
+            match (words.next(), words.next(), words.next()) {
+                (Some("ifdef"), Some(name), None) => {
+                    conditions.push(self.shader_defs.contains(&name));
+                }
+                (Some("ifndef"), Some(name), None) => {
+                    conditions.push(!self.shader_defs.contains(&name));
+                }
+                (Some("else"), None, None) => {
+                    let keep = conditions
+                        .last_mut()
+                        .ok_or(ShaderComposeError::UnmatchedElse(location))?;
+                    *keep = !*keep;
+                }
+                (Some("endif"), None, None) => {
+                    conditions
+                        .pop()
+                        .ok_or(ShaderComposeError::UnmatchedEndif(location))?;
+                }
+                (Some("import"), Some(name), None) => {
+                    if !conditions.iter().all(|&keep| keep) {
+                        continue;
+                    }
+                    let &(name, module_source) = MODULES
+                        .iter()
+                        .find(|(module_name, _)| *module_name == name)
+                        .ok_or_else(|| {
+                            ShaderComposeError::UnknownImport(location, name.to_string())
+                        })?;
+                    // inserting before composing makes modules that import each other work
+                    if self.imported.insert(name) {
+                        self.compose(Some(name), (self.module_source)(name, module_source))?;
+                    }
+                }
+                _ => {
+                    return Err(ShaderComposeError::InvalidDirective(
+                        location,
+                        line.trim().to_string(),
+                    ))
+                }
+            }
+        }
+
+        if !conditions.is_empty() {
+            return Err(ShaderComposeError::MissingEndif(module));
+        }
+        Ok(())
+    }
+}
+
+/// where in a shader an error happened
+#[derive(Clone, Copy, Debug)]
+pub struct ShaderLocation {
+    /// the name of the imported module, or `None` for the shader itself
+    pub module: Option<&'static str>,
+    pub line: usize,
+}
+
+impl fmt::Display for ShaderLocation {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self.module {
+            Some(module) => write!(f, "module {module:?} line {}", self.line),
+            None => write!(f, "line {}", self.line),
+        }
+    }
+}
+
+#[derive(Debug)]
+pub enum ShaderComposeError {
+    UnknownImport(ShaderLocation, String),
+    InvalidDirective(ShaderLocation, String),
+    UnmatchedElse(ShaderLocation),
+    UnmatchedEndif(ShaderLocation),
+    /// an `#ifdef` or `#ifndef` in the shader or module is never closed
+    MissingEndif(Option<&'static str>),
+}
+
+impl fmt::Display for ShaderComposeError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ShaderComposeError::UnknownImport(location, name) => {
+                write!(f, "{location}: there is no shader module called {name:?}")
+            }
+            ShaderComposeError::InvalidDirective(location, directive) => {
+                write!(f, "{location}: invalid directive {directive:?}")
+            }
+            ShaderComposeError::UnmatchedElse(location) => {
+                write!(f, "{location}: #else without #ifdef")
+            }
+            ShaderComposeError::UnmatchedEndif(location) => {
+                write!(f, "{location}: #endif without #ifdef")
+            }
+            ShaderComposeError::MissingEndif(Some(module)) => {
+                write!(f, "module {module:?}: #ifdef without #endif")
+            }
+            ShaderComposeError::MissingEndif(None) => write!(f, "#ifdef without #endif"),
+        }
+    }
+}
+
+impl std::error::Error for ShaderComposeError {}
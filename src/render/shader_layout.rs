@@ -0,0 +1,174 @@
+// encase only exposes the offsets of struct fields through the metadata its derive macro uses
+use encase::{private::StructMetadata, ShaderSize, ShaderType};
+use std::fmt;
+
+/// the offset and size of every field of a struct and the size of the whole struct in bytes
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct StructLayout {
+    pub fields: Vec<(u64, u64)>,
+    pub size: u64,
+}
+
+/// a struct that derives [`ShaderType`], so its layout can be compared with a wgsl struct
+pub trait ShaderStruct {
+    fn struct_layout() -> StructLayout;
+}
+
+impl<T: ShaderType + ShaderSize> ShaderStruct for T
+where
+    T::ExtraMetadata: FieldMetadata,
+{
+    fn struct_layout() -> StructLayout {
+        let size = T::SHADER_SIZE.get();
+        let metadata = T::METADATA;
+        let (offsets, paddings) = metadata.extra.offsets_and_paddings();
+        let fields = (0..offsets.len())
+            .map(|index| {
+                let end = offsets.get(index + 1).copied().unwrap_or(size);
+                // the padding after a field belongs to the struct, not to the field
+                (offsets[index], end - offsets[index] - paddings[index])
+            })
+            .collect();
+        StructLayout { fields, size }
+    }
+}
+
+#[doc(hidden)]
+pub trait FieldMetadata {
+    fn offsets_and_paddings(&self) -> (&[u64], &[u64]);
+}
+
+impl<const N: usize> FieldMetadata for StructMetadata<N> {
+    fn offsets_and_paddings(&self) -> (&[u64], &[u64]) {
+        (&self.offsets, &self.paddings)
+    }
+}
+
+/// parses and validates `source` and checks that every struct in `structs` has the same layout as the wgsl struct with the same name,
+/// so a rust struct that does not match the shader is reported instead of showing up as garbage on screen
+pub fn validate_struct_layouts(
+    source: &str,
+    structs: &[(&'static str, StructLayout)],
+) -> Result<(), ShaderLayoutError> {
+    let module = naga::front::wgsl::parse_str(source)
+        .map_err(|error| ShaderLayoutError::Invalid(error.emit_to_string(source)))?;
+    naga::valid::Validator::new(
+        naga::valid::ValidationFlags::all(),
+        naga::valid::Capabilities::empty(),
+    )
+    .validate(&module)
+    .map_err(|error| ShaderLayoutError::Invalid(error.emit_to_string(source)))?;
+    let mut layouter = naga::proc::Layouter::default();
+    layouter
+        .update(module.to_ctx())
+        .map_err(|error| ShaderLayoutError::Invalid(error.to_string()))?;
+
+    for (name, rust_layout) in structs {
+        let Some((handle, members)) =
+            module
+                .types
+                .iter()
+                .find_map(|(handle, ty)| match &ty.inner {
+                    naga::TypeInner::Struct { members, .. } if ty.name.as_deref() == Some(name) => {
+                        Some((handle, members))
+                    }
+                    _ => None,
+                })
+        else {
+            return Err(ShaderLayoutError::MissingStruct(name));
+        };
+
+        let wgsl_layout = StructLayout {
+            fields: members
+                .iter()
+                .map(|member| (member.offset as u64, layouter[member.ty].size as u64))
+                .collect(),
+            size: layouter[handle].size as u64,
+        };
+        if wgsl_layout.fields.len() != rust_layout.fields.len() {
+            return Err(ShaderLayoutError::FieldCount {
+                name,
+                rust: rust_layout.fields.len(),
+                wgsl: wgsl_layout.fields.len(),
+            });
+        }
+        for (index, (rust, wgsl)) in rust_layout
+            .fields
+            .iter()
+            .zip(&wgsl_layout.fields)
+            .enumerate()
+        {
+            if rust != wgsl {
+                return Err(ShaderLayoutError::Field {
+                    name,
+                    field: members[index].name.clone().unwrap_or_default(),
+                    rust: *rust,
+                    wgsl: *wgsl,
+                });
+            }
+        }
+        if rust_layout.size != wgsl_layout.size {
+            return Err(ShaderLayoutError::Size {
+                name,
+                rust: rust_layout.size,
+                wgsl: wgsl_layout.size,
+            });
+        }
+    }
+    Ok(())
+}
+
+#[derive(Debug)]
+pub enum ShaderLayoutError {
+    /// the shader could not be parsed or is not valid, with the message of naga
+    Invalid(String),
+    MissingStruct(&'static str),
+    FieldCount {
+        name: &'static str,
+        rust: usize,
+        wgsl: usize,
+    },
+    /// the offset and size of a field differ, `field` is the name of the wgsl field
+    Field {
+        name: &'static str,
+        field: String,
+        rust: (u64, u64),
+        wgsl: (u64, u64),
+    },
+    Size {
+        name: &'static str,
+        rust: u64,
+        wgsl: u64,
+    },
+}
+
+impl fmt::Display for ShaderLayoutError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ShaderLayoutError::Invalid(message) => write!(f, "the shader is invalid: {message}"),
+            ShaderLayoutError::MissingStruct(name) => {
+                write!(f, "the shader has no struct called {name:?}")
+            }
+            ShaderLayoutError::FieldCount { name, rust, wgsl } => write!(
+                f,
+                "struct {name:?} has {rust} fields in rust but {wgsl} fields in wgsl"
+            ),
+            ShaderLayoutError::Field {
+                name,
+                field,
+                rust: (rust_offset, rust_size),
+                wgsl: (wgsl_offset, wgsl_size),
+            } => write!(
+                f,
+                "field {field:?} of struct {name:?} is at offset {rust_offset} with size {rust_size} in rust \
+                but at offset {wgsl_offset} with size {wgsl_size} in wgsl"
+            ),
+            ShaderLayoutError::Size { name, rust, wgsl } => write!(
+                f,
+                "struct {name:?} has a size of {rust} bytes in rust but {wgsl} bytes in wgsl"
+            ),
+        }
+    }
+}
+
+impl std::error::Error for ShaderLayoutError {}
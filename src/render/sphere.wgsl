@@ -0,0 +1,61 @@
+struct Sphere {
+    transform: Motor,
+    previous_transform: Motor,
+    radius: f32,
+    // 0 for a surface
+    medium_density: f32,
+    medium_anisotropy: f32,
+    material: u32,
+}
+
+fn intersect_sphere(ray: Ray, sphere: Sphere) -> Hit {
+    var hit: Hit;
+    hit.hit = false;
+    hit.material = sphere.material;
+
+    let transform = interpolate_motor(sphere.previous_transform, sphere.transform, ray.time);
+    let sphere_position = point_to_vec3(transform_point(vec3_to_point(vec3<f32>(0.0)), transform));
+    let oc = ray.origin - sphere_position;
+    let a = dot(ray.direction, ray.direction);
+    let half_b = dot(oc, ray.direction);
+    let c = dot(oc, oc) - sphere.radius * sphere.radius;
+    let discriminant = half_b * half_b - a * c;
+
+    if discriminant < 0.0 {
+        return hit;
+    }
+
+    let sqrt_discriminant = sqrt(discriminant);
+    let t0 = (-half_b - sqrt_discriminant) / a;
+    let t1 = (-half_b + sqrt_discriminant) / a;
+
+    if sphere.medium_density > 0.0 {
+        // the ray enters the medium at the origin when it starts inside
+        if t1 < camera.min_distance || camera.max_distance < t0 {
+            return hit;
+        }
+        hit.distance = max(t0, camera.min_distance);
+        hit.exit_distance = min(t1, camera.max_distance);
+        hit.medium_density = sphere.medium_density;
+        hit.medium_anisotropy = sphere.medium_anisotropy;
+    } else {
+        if t0 > camera.min_distance {
+            hit.distance = t0;
+        } else {
+            hit.distance = t1;
+        }
+
+        if hit.distance < camera.min_distance || camera.max_distance < hit.distance {
+            return hit;
+        }
+    }
+
+    hit.position = ray.origin + ray.direction * hit.distance;
+    hit.normal = normalize(hit.position - sphere_position);
+    if dot(hit.normal, ray.origin - hit.position) < 0.0 {
+        hit.normal *= -1.0;
+    }
+
+    hit.hit = true;
+    return hit;
+}
@@ -0,0 +1,181 @@
+use crate::render::{post_process::MAIN_TEXTURE_FORMAT, render_state};
+use bevy::ecs::world::World;
+use std::{fmt, path::Path};
+
+/// renders the main camera into an image of any size, split into tiles that are submitted to the gpu one after another,
+/// so no single submission runs long enough to trigger a gpu timeout
+///
+/// the image is rendered from the scene the [`RenderSchedule`](super::RenderSchedule) uploaded last,
+/// it is the raw output of the ray tracer, [`RenderedImage::post_process`] applies the post processing
+#[derive(Clone, Copy, Debug)]
+pub struct TiledRender {
+    pub width: u32,
+    pub height: u32,
+    /// rays per pixel, they are averaged
+    pub samples: u32,
+    /// the samples of a tile are split into submissions of at most this many, so many samples don't trigger a gpu timeout either
+    pub samples_per_submission: u32,
+    /// the width and height of a tile in pixels, smaller tiles take less time each
+    pub tile_size: u32,
+}
+
+impl Default for TiledRender {
+    fn default() -> Self {
+        Self {
+            width: 1920,
+            height: 1080,
+            samples: 1,
+            samples_per_submission: 16,
+            tile_size: 256,
+        }
+    }
+}
+
+impl TiledRender {
+    /// renders every tile and waits for the gpu, `progress` is called after each tile
+    pub fn render(
+        &self,
+        world: &mut World,
+        progress: impl FnMut(TileProgress),
+    ) -> Result<RenderedImage, TiledRenderError> {
+        render_state::render_tiled(world, self, progress)
+    }
+
+    pub(super) fn tiles_x(&self) -> u32 {
+        self.width.div_ceil(self.tile_size)
+    }
+
+    pub(super) fn tile_count(&self) -> u32 {
+        self.tiles_x() * self.height.div_ceil(self.tile_size)
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct TileProgress {
+    pub finished_tiles: u32,
+    pub tile_count: u32,
+}
+
+impl TileProgress {
+    /// from 0 to 1
+    pub fn fraction(&self) -> f32 {
+        self.finished_tiles as f32 / self.tile_count.max(1) as f32
+    }
+}
+
+/// a linear hdr image, the rows go from the top to the bottom
+#[derive(Clone, Debug)]
+pub struct RenderedImage {
+    pub width: u32,
+    pub height: u32,
+    /// rgba, `width * height` pixels
+    pub pixels: Vec<[f32; 4]>,
+}
+
+impl RenderedImage {
+    /// saves the image as an 8 bit png, colors are clamped to 0 to 1 and stored like the post processing stores them for the window
+    pub fn save_png(&self, path: impl AsRef<Path>) -> image::ImageResult<()> {
+        // the main texture is copied to the window without conversion, so it is only encoded when its format is srgb
+        let to_byte = |color: f32| {
+            let color = color.clamp(0.0, 1.0);
+            let color = if MAIN_TEXTURE_FORMAT.is_srgb() {
+                linear_to_srgb(color)
+            } else {
+                color
+            };
+            (color * 255.0).round() as u8
+        };
+        image::RgbaImage::from_fn(self.width, self.height, |x, y| {
+            let [r, g, b, _] = self.pixels[y as usize * self.width as usize + x as usize];
+            image::Rgba([to_byte(r), to_byte(g), to_byte(b), u8::MAX])
+        })
+        .save_with_format(path, image::ImageFormat::Png)
+    }
+
+    /// runs the post processing of the main camera on the image on the gpu, like for the window,
+    /// the image has to fit into a single texture, because effects like bloom read the pixels around each pixel
+    pub fn post_process(&self, world: &World) -> Result<image::RgbaImage, TiledRenderError> {
+        render_state::post_process_image(world, self)
+    }
+
+    /// saves the linear hdr colors as an openexr image
+    pub fn save_exr(&self, path: impl AsRef<Path>) -> image::ImageResult<()> {
+        image::Rgba32FImage::from_fn(self.width, self.height, |x, y| {
+            image::Rgba(self.pixels[y as usize * self.width as usize + x as usize])
+        })
+        .save_with_format(path, image::ImageFormat::OpenExr)
+    }
+}
+
+#[derive(Debug)]
+pub enum TiledRenderError {
+    /// there is no renderer, see [`RenderStatus`](super::RenderStatus)
+    Unavailable,
+    /// the width, height, samples, samples per submission or tile size is 0, or the tile size is larger than the gpu supports,
+    /// or the image is too large to post process it
+    InvalidSize,
+    /// reading a tile back from the gpu failed, usually because the device was lost
+    Readback,
+}
+
+impl fmt::Display for TiledRenderError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            TiledRenderError::Unavailable => write!(f, "there is no renderer to render with"),
+            TiledRenderError::InvalidSize => write!(
+                f,
+                "the image, sample counts and tile size have to be larger than 0 and the tiles have to fit into a texture"
+            ),
+            TiledRenderError::Readback => write!(f, "failed to read a tile back from the gpu"),
+        }
+    }
+}
+
+impl std::error::Error for TiledRenderError {}
+
+pub(super) fn linear_to_srgb(linear: f32) -> f32 {
+    if linear <= 0.0031308 {
+        linear * 12.92
+    } else {
+        1.055 * linear.powf(1.0 / 2.4) - 0.055
+    }
+}
+
+// converts an f32 to an f16 of the hdr texture, rounding to the closest f16
+pub(super) fn f32_to_f16(value: f32) -> u16 {
+    let bits = value.to_bits();
+    let sign = (bits >> 16 & 0x8000) as u16;
+    let exponent = (bits >> 23 & 0xff) as i32;
+    let mantissa = bits & 0x7f_ffff;
+    if exponent == 0xff {
+        return sign | 0x7c00 | if mantissa != 0 { 0x200 } else { 0 };
+    }
+    let exponent = exponent - 127 + 15;
+    if exponent >= 0x1f {
+        return sign | 0x7c00;
+    }
+    if exponent <= 0 {
+        if exponent < -10 {
+            return sign;
+        }
+        // subnormal, the implicit leading bit of the mantissa is stored
+        let mantissa = mantissa | 0x80_0000;
+        let shift = 14 - exponent;
+        return sign | ((mantissa + (1 << (shift - 1))) >> shift) as u16;
+    }
+    // a mantissa that rounds up carries into the exponent, which is still the closest f16
+    sign | (((exponent as u32) << 10 | mantissa >> 13) + (mantissa >> 12 & 1)) as u16
+}
+
+// converts an f16 of the hdr texture to an f32
+pub(super) fn f16_to_f32(bits: u16) -> f32 {
+    let sign = if bits & 0x8000 != 0 { -1.0 } else { 1.0 };
+    let exponent = (bits >> 10 & 0x1f) as i32;
+    let mantissa = (bits & 0x3ff) as f32;
+    match exponent {
+        0 => sign * mantissa * 2.0f32.powi(-24),
+        0x1f if mantissa == 0.0 => sign * f32::INFINITY,
+        0x1f => f32::NAN,
+        _ => sign * (1.0 + mantissa / 1024.0) * 2.0f32.powi(exponent - 15),
+    }
+}
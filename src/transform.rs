@@ -26,6 +26,7 @@ impl Plugin for TransformPlugin {
                         remove_global_transforms,
                         update_removed_parents,
                     ),
+                    update_previous_global_transforms,
                     update_global_transforms,
                 )
                     .chain()
@@ -51,12 +52,25 @@ impl GlobalTransform {
     }
 }
 
+/// the [`GlobalTransform`] of the previous update, the renderer uses it for motion blur
+#[derive(Component, Clone, Copy)]
+pub struct PreviousGlobalTransform(Transform);
+
+impl PreviousGlobalTransform {
+    pub fn transform(&self) -> Transform {
+        self.0
+    }
+}
+
 fn add_global_transforms(
     mut commands: Commands,
     transforms: Query<(Entity, &Transform), Without<GlobalTransform>>,
 ) {
     transforms.for_each(|(entity, &transform)| {
-        commands.entity(entity).insert(GlobalTransform(transform));
+        commands.entity(entity).insert((
+            GlobalTransform(transform),
+            PreviousGlobalTransform(transform),
+        ));
     });
 }
 
@@ -65,7 +79,9 @@ fn remove_global_transforms(
     global_transforms: Query<Entity, (With<GlobalTransform>, Without<Transform>)>,
 ) {
     global_transforms.for_each(|entity| {
-        commands.entity(entity).remove::<GlobalTransform>();
+        commands
+            .entity(entity)
+            .remove::<(GlobalTransform, PreviousGlobalTransform)>();
     });
 }
 
@@ -84,7 +100,20 @@ fn update_removed_parents(
         });
 }
 
-fn update_global_transforms(
+fn update_previous_global_transforms(
+    mut transforms: Query<(&GlobalTransform, &mut PreviousGlobalTransform)>,
+) {
+    transforms
+        .par_iter_mut()
+        .for_each(|(global_transform, mut previous_global_transform)| {
+            // only set when the entity moved, so entities that stopped moving are not changed every update
+            if previous_global_transform.0.motor != global_transform.0.motor {
+                previous_global_transform.0 = global_transform.0;
+            }
+        });
+}
+
+pub(crate) fn update_global_transforms(
     mut global_transforms: Query<(Entity, &mut GlobalTransform)>,
     transforms: Query<(Ref<Transform>, Option<Ref<Parent>>)>,
 ) {